@@ -3,6 +3,7 @@
 // conditions defined in the file COPYING, which is part of this source code package.
 
 use std::fmt::{Display, Formatter, Result as FormatResult};
+use std::io::Write;
 use std::mem;
 use std::str::FromStr;
 use typed_builder::TypedBuilder;
@@ -120,6 +121,14 @@ impl LevelsStrategy {
             Self::Lower => PartialOrd::lt(x, y),
         }
     }
+
+    /// How a value that triggers the levels relates to them.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Upper => "above",
+            Self::Lower => "below",
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -150,6 +159,9 @@ pub struct LevelsCheckerArgs {
 pub struct LevelsChecker<T> {
     strategy: LevelsStrategy,
     levels: Levels<T>,
+    /// Without crit level, `levels.crit` is a copy of the warn level
+    /// that is neither evaluated nor part of the metric.
+    warn_only: bool,
 }
 
 impl<T> LevelsChecker<T>
@@ -162,13 +174,29 @@ where
     ) -> Result<Self, Box<dyn std::error::Error>> {
         strategy
             .cmp(&levels.crit, &levels.warn)
-            .then_some(Self { strategy, levels })
+            .then_some(Self {
+                strategy,
+                levels,
+                warn_only: false,
+            })
             .ok_or(Box::from("bad values"))
     }
 
+    /// Levels that never go beyond WARN.
+    pub fn warn_only(strategy: LevelsStrategy, warn: T) -> Self {
+        Self {
+            strategy,
+            levels: Levels {
+                warn: warn.clone(),
+                crit: warn,
+            },
+            warn_only: true,
+        }
+    }
+
     pub fn check(&self, value: T, output: OutputType, args: LevelsCheckerArgs) -> CheckResult<T> {
         let evaluate = |value: &T| -> State {
-            if self.strategy.cmp(value, &self.levels.crit) {
+            if !self.warn_only && self.strategy.cmp(value, &self.levels.crit) {
                 State::Crit
             } else if self.strategy.cmp(value, &self.levels.warn) {
                 State::Warn
@@ -177,11 +205,16 @@ where
             }
         };
         let state = evaluate(&value);
-        let (summary, details) = match (output, state) {
-            (OutputType::Notice(text), State::Ok) => (None, Some(text)),
-            (OutputType::Notice(text), _) => (Some(text), None),
-            (OutputType::Summary(text), _) => (Some(text), None),
+        let output = match state {
+            State::Warn => {
+                output.map(|text| format!("{text} ({} warn level)", self.strategy.as_str()))
+            }
+            State::Crit => {
+                output.map(|text| format!("{text} ({} crit level)", self.strategy.as_str()))
+            }
+            _ => output,
         };
+        let (summary, details) = output.split(state);
         CheckResult {
             state,
             summary,
@@ -191,8 +224,37 @@ where
                 value,
                 uom: args.uom,
                 levels: Some(self.levels.clone()),
+                warn_only: self.warn_only,
                 bounds: None,
             }),
+            tag: None,
+        }
+    }
+}
+
+/// Evaluate `value` against `levels` or emit the metric alone if there are no levels.
+pub fn check_levels<T>(
+    value: T,
+    levels: Option<LevelsChecker<T>>,
+    output: OutputType,
+    args: LevelsCheckerArgs,
+) -> CheckResult<T>
+where
+    T: Clone + PartialOrd,
+{
+    match levels {
+        Some(levels) => levels.check(value, output, args),
+        None => {
+            let metric = Metric {
+                label: args.label,
+                value,
+                uom: args.uom,
+                levels: None,
+                warn_only: false,
+                bounds: None,
+            };
+            let (summary, details) = output.split(State::Ok);
+            CheckResult::new(State::Ok, summary, details, Some(metric))
         }
     }
 }
@@ -201,6 +263,30 @@ where
 pub enum OutputType {
     Summary(String),
     Notice(String),
+    /// A notice followed by lines that stay in the details whatever the state.
+    Details(String, String),
+}
+
+impl OutputType {
+    fn map<F: FnOnce(String) -> String>(self, f: F) -> Self {
+        match self {
+            Self::Summary(text) => Self::Summary(f(text)),
+            Self::Notice(text) => Self::Notice(f(text)),
+            Self::Details(text, details) => Self::Details(f(text), details),
+        }
+    }
+
+    fn split(self, state: State) -> (Option<String>, Option<String>) {
+        match (self, state) {
+            (Self::Notice(text), State::Ok) => (None, as_option(text)),
+            (Self::Notice(text), _) => (as_option(text), None),
+            (Self::Summary(text), _) => (as_option(text), None),
+            (Self::Details(text, details), State::Ok) => {
+                (None, as_option(format!("{text}\n{details}").trim()))
+            }
+            (Self::Details(text, details), _) => (as_option(text), as_option(details)),
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -237,6 +323,9 @@ where
     uom: Option<Uom>,
     #[builder(default, setter(strip_option))]
     levels: Option<Levels<T>>,
+    /// Leave the crit level out.
+    #[builder(default)]
+    warn_only: bool,
     #[builder(default, setter(strip_option))]
     bounds: Option<Bounds<T>>,
 }
@@ -256,6 +345,7 @@ where
             value: f(self.value),
             uom: self.uom,
             levels: self.levels.map(|v| v.map(f)),
+            warn_only: self.warn_only,
             bounds: self.bounds.map(|v| v.map(f)),
         }
     }
@@ -279,6 +369,7 @@ where
                 .map_or(Default::default(), |v| v.warn.to_string()),
             self.levels
                 .as_ref()
+                .filter(|_| !self.warn_only)
                 .map_or(Default::default(), |v| v.crit.to_string()),
             self.bounds
                 .as_ref()
@@ -296,6 +387,7 @@ pub struct SimpleCheckResult {
     state: State,
     summary: Option<String>,
     details: Option<String>,
+    tag: Option<String>,
 }
 
 fn as_option(s: impl Into<String>) -> Option<String> {
@@ -309,9 +401,16 @@ impl SimpleCheckResult {
             state,
             summary,
             details,
+            tag: None,
         }
     }
 
+    /// Name the sub-check for automation, see [`Collection::show_tags`].
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = as_option(tag);
+        self
+    }
+
     pub fn notice(details: impl Into<String>) -> Self {
         Self::new(State::Ok, None, as_option(details))
     }
@@ -353,6 +452,7 @@ where
     summary: Option<String>,
     details: Option<String>,
     metrics: Option<Metric<T>>,
+    tag: Option<String>,
 }
 
 impl<T> Default for CheckResult<T>
@@ -379,9 +479,16 @@ where
             summary,
             details,
             metrics,
+            tag: None,
         }
     }
 
+    /// Name the sub-check for automation, see [`Collection::show_tags`].
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = as_option(tag);
+        self
+    }
+
     pub fn notice(details: impl Into<String>, metrics: Metric<T>) -> Self {
         Self::new(State::Ok, None, as_option(details), Some(metrics))
     }
@@ -457,6 +564,7 @@ where
             summary: self.summary,
             details: self.details,
             metrics: self.metrics.map(|m| m.map(f)),
+            tag: self.tag,
         }
     }
 }
@@ -471,15 +579,16 @@ where
             summary: x.summary,
             details: x.details,
             metrics: None,
+            tag: x.tag,
         }
     }
 }
 
 #[derive(Debug)]
 enum Details {
-    Text(String),
+    Text(Option<String>, String),
     Metric(Metric<Real>),
-    TextMetric(String, Metric<Real>),
+    TextMetric(Option<String>, String, Metric<Real>),
 }
 
 #[derive(Debug)]
@@ -503,9 +612,9 @@ impl<'a> IntoIterator for &'a Details {
 
     fn into_iter(self) -> Self::IntoIter {
         match self {
-            Details::Text(t) => vec![FlatDetailsView::Text(t)].into_iter(),
+            Details::Text(_, t) => vec![FlatDetailsView::Text(t)].into_iter(),
             Details::Metric(m) => vec![FlatDetailsView::Metric(m)].into_iter(),
-            Details::TextMetric(t, m) => {
+            Details::TextMetric(_, t, m) => {
                 vec![FlatDetailsView::Text(t), FlatDetailsView::Metric(m)].into_iter()
             }
         }
@@ -516,6 +625,7 @@ impl<'a> IntoIterator for &'a Details {
 struct Summary {
     state: State,
     text: Option<String>,
+    tag: Option<String>,
 }
 
 #[derive(Debug, Default)]
@@ -531,6 +641,65 @@ impl Collection {
         self.summary.append(&mut other.summary);
         self.details.append(&mut other.details);
     }
+
+    /// Tell the results of several targets apart in a joined output.
+    ///
+    /// The first summary names the target and the metric labels get it as a prefix.
+    pub fn namespace(&mut self, name: &str) {
+        let prefix = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        for details in &mut self.details {
+            if let Details::Metric(metric) | Details::TextMetric(_, _, metric) = details {
+                metric.label = format!("{prefix}_{}", metric.label);
+            }
+        }
+        match self.summary.iter_mut().find_map(|s| s.text.as_mut()) {
+            Some(text) => *text = format!("{name}: {text}"),
+            None => self.summary.insert(
+                0,
+                Summary {
+                    state: State::Ok,
+                    text: Some(format!("{name}: {}", self.state.as_str())),
+                    tag: None,
+                },
+            ),
+        }
+    }
+
+    /// Drop the details but keep the state, the summary and the metrics.
+    pub fn compact(&mut self) {
+        self.details = std::mem::take(&mut self.details)
+            .into_iter()
+            .filter_map(|details| match details {
+                Details::Text(..) => None,
+                Details::Metric(metric) | Details::TextMetric(_, _, metric) => {
+                    Some(Details::Metric(metric))
+                }
+            })
+            .collect();
+    }
+
+    /// Prefix the text of the tagged results with a stable `[tag]`, on the
+    /// summary if the result has one and on its notice line otherwise.
+    pub fn show_tags(&mut self) {
+        let prefix = |tag: &mut Option<String>, text: &mut String| {
+            if let Some(tag) = tag.take() {
+                *text = format!("[{tag}] {text}");
+            }
+        };
+        for summary in &mut self.summary {
+            if let Some(text) = &mut summary.text {
+                prefix(&mut summary.tag, text);
+            }
+        }
+        for details in &mut self.details {
+            if let Details::Text(tag, text) | Details::TextMetric(tag, text, _) = details {
+                prefix(tag, text);
+            }
+        }
+    }
 }
 
 impl Display for Collection {
@@ -591,15 +760,20 @@ impl From<SimpleCheckResult> for Collection {
     fn from(check_result: SimpleCheckResult) -> Self {
         Self {
             state: check_result.state,
-            summary: vec![Summary {
-                state: check_result.state,
-                text: check_result.summary,
-            }],
             details: {
+                let tag = check_result
+                    .tag
+                    .clone()
+                    .filter(|_| check_result.summary.is_none());
                 let mut v = vec![];
-                v.extend(check_result.details.map(Details::Text));
+                v.extend(check_result.details.map(|text| Details::Text(tag, text)));
                 v
             },
+            summary: vec![Summary {
+                state: check_result.state,
+                tag: check_result.tag.filter(|_| check_result.summary.is_some()),
+                text: check_result.summary,
+            }],
         }
     }
 }
@@ -610,15 +784,23 @@ impl From<&mut Vec<CheckResult<Real>>> for Collection {
             .drain(..)
             .fold(Collection::default(), |mut out, cr| {
                 out.state = std::cmp::max(out.state, cr.state);
+                // The tag goes to the summary if there is one, to the notice otherwise.
+                let (summary_tag, details_tag) = match cr.summary {
+                    Some(_) => (cr.tag, None),
+                    None => (None, cr.tag),
+                };
                 out.summary.push(Summary {
                     state: cr.state,
                     text: cr.summary,
+                    tag: summary_tag,
                 });
                 out.details.extend(match (cr.details, cr.metrics) {
                     (None, None) => vec![],
-                    (Some(text), None) => vec![Details::Text(text)],
+                    (Some(text), None) => vec![Details::Text(details_tag, text)],
                     (None, Some(metric)) => vec![Details::Metric(metric)],
-                    (Some(text), Some(metric)) => vec![Details::TextMetric(text, metric)],
+                    (Some(text), Some(metric)) => {
+                        vec![Details::TextMetric(details_tag, text, metric)]
+                    }
                 });
                 out
             })
@@ -640,6 +822,29 @@ pub fn bail_out(message: impl Into<String>) -> ! {
     std::process::exit(exit_code(&out))
 }
 
+/// Report panics as UNKNOWN instead of dumping a backtrace the monitoring cannot parse.
+pub fn bail_out_on_panic() {
+    std::panic::set_hook(Box::new(|info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown error");
+        let out = Collection::from(SimpleCheckResult::unknown(match info.location() {
+            Some(location) => format!(
+                "Internal error: {message} ({}:{})",
+                location.file(),
+                location.line()
+            ),
+            None => format!("Internal error: {message}"),
+        }));
+        // Not `println!`, the panic may come from printing to a closed stdout.
+        let _ = writeln!(std::io::stdout(), "{out}");
+        std::process::exit(exit_code(&out))
+    }));
+}
+
 pub fn abort(message: impl Into<String>) -> ! {
     let out = Collection::from(SimpleCheckResult::crit(message));
     println!("{}", out);
@@ -816,6 +1021,198 @@ mod test_metrics_display {
     }
 }
 
+#[cfg(test)]
+mod test_check_levels {
+    use super::{
+        check_levels, Collection, Levels, LevelsChecker, LevelsCheckerArgs, LevelsStrategy,
+        OutputType, Real,
+    };
+
+    fn args() -> LevelsCheckerArgs {
+        LevelsCheckerArgs::builder()
+            .label("name")
+            .uom("s".parse().unwrap())
+            .build()
+    }
+
+    #[test]
+    fn test_without_levels() {
+        let cr = check_levels(42, None, OutputType::Notice("notice".to_string()), args());
+        assert_eq!(
+            Collection::from(&mut vec![cr.map(|x| Real::from(x as isize))]).to_string(),
+            "OK | name=42s;;;;\nnotice"
+        );
+    }
+
+    #[test]
+    fn test_with_levels() {
+        let levels = LevelsChecker::try_new(LevelsStrategy::Upper, Levels { warn: 10, crit: 20 });
+        let cr = check_levels(
+            42,
+            Some(levels.unwrap()),
+            OutputType::Notice("notice".to_string()),
+            args(),
+        );
+        assert_eq!(
+            Collection::from(&mut vec![cr.map(|x| Real::from(x as isize))]).to_string(),
+            "notice (above crit level) (!!) | name=42s;10;20;;"
+        );
+    }
+
+    #[test]
+    fn test_with_lower_levels() {
+        let levels = LevelsChecker::try_new(LevelsStrategy::Lower, Levels { warn: 20, crit: 10 });
+        let cr = check_levels(
+            15,
+            Some(levels.unwrap()),
+            OutputType::Summary("summary".to_string()),
+            args(),
+        );
+        assert_eq!(
+            Collection::from(&mut vec![cr.map(|x| Real::from(x as isize))]).to_string(),
+            "summary (below warn level) (!) | name=15s;20;10;;"
+        );
+    }
+
+    #[test]
+    fn test_warn_only() {
+        let levels = || LevelsChecker::warn_only(LevelsStrategy::Upper, 10);
+        for (value, text) in [
+            (5, "OK | name=5s;10;;;\nnotice"),
+            (42, "notice (above warn level) (!) | name=42s;10;;;"),
+        ] {
+            let cr = check_levels(
+                value,
+                Some(levels()),
+                OutputType::Notice("notice".to_string()),
+                args(),
+            );
+            assert_eq!(
+                Collection::from(&mut vec![cr.map(|x| Real::from(x as isize))]).to_string(),
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_details_ok() {
+        let cr = check_levels(
+            42,
+            None,
+            OutputType::Details("notice".to_string(), "line 1\nline 2".to_string()),
+            args(),
+        );
+        assert_eq!(
+            Collection::from(&mut vec![cr.map(|x| Real::from(x as isize))]).to_string(),
+            "OK | name=42s;;;;\nnotice\nline 1\nline 2"
+        );
+    }
+
+    #[test]
+    fn test_details_crit() {
+        let levels = LevelsChecker::try_new(LevelsStrategy::Upper, Levels { warn: 10, crit: 20 });
+        let cr = check_levels(
+            42,
+            Some(levels.unwrap()),
+            OutputType::Details("notice".to_string(), "line 1\nline 2".to_string()),
+            args(),
+        );
+        assert_eq!(
+            Collection::from(&mut vec![cr.map(|x| Real::from(x as isize))]).to_string(),
+            "notice (above crit level) (!!) | name=42s;10;20;;\nline 1\nline 2"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_namespace {
+    use super::{CheckResult, Collection, Metric, Real, SimpleCheckResult};
+
+    #[test]
+    fn test_namespace() {
+        let mut coll = Collection::from(&mut vec![
+            SimpleCheckResult::notice("details").into(),
+            CheckResult::ok(
+                "summary",
+                Metric::builder().label("time").value(Real::from(1)).build(),
+            ),
+        ]);
+        coll.namespace("example.com:443");
+        assert_eq!(
+            coll.to_string(),
+            "example.com:443: summary | example_com_443_time=1;;;;\ndetails"
+        );
+    }
+
+    #[test]
+    fn test_namespace_without_summary() {
+        let mut coll = Collection::from(SimpleCheckResult::notice("details"));
+        coll.namespace("example.com:443");
+        let mut other = Collection::from(SimpleCheckResult::warn("summary"));
+        other.namespace("other.com:443");
+        coll.join(&mut other);
+        assert_eq!(
+            coll.to_string(),
+            "example.com:443: OK, other.com:443: summary (!)\ndetails"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_compact {
+    use super::{CheckResult, Collection, Metric, Real, SimpleCheckResult};
+
+    #[test]
+    fn test_compact() {
+        let mut coll = Collection::from(&mut vec![
+            SimpleCheckResult::notice("details").into(),
+            CheckResult::notice(
+                "notice",
+                Metric::builder().label("a").value(Real::from(1)).build(),
+            ),
+            CheckResult::warn(
+                "summary",
+                Metric::builder().label("b").value(Real::from(2)).build(),
+            ),
+        ]);
+        let verbose = coll.to_string();
+        coll.compact();
+        assert_eq!(coll.to_string(), "summary (!) | a=1;;;; b=2;;;;");
+        assert!(verbose.starts_with("summary (!) | a=1;;;; b=2;;;;\n"));
+    }
+}
+
+#[cfg(test)]
+mod test_show_tags {
+    use super::{CheckResult, Collection, Metric, Real, SimpleCheckResult};
+
+    #[test]
+    fn test_show_tags() {
+        let coll = || {
+            Collection::from(&mut vec![
+                SimpleCheckResult::notice("details").tag("a").into(),
+                CheckResult::notice(
+                    "notice",
+                    Metric::builder().label("b").value(Real::from(1)).build(),
+                )
+                .tag("b"),
+                SimpleCheckResult::crit("summary").tag("c").into(),
+                SimpleCheckResult::notice("untagged").into(),
+            ])
+        };
+        assert_eq!(
+            coll().to_string(),
+            "summary (!!) | b=1;;;;\ndetails\nnotice\nuntagged"
+        );
+        let mut tagged = coll();
+        tagged.show_tags();
+        assert_eq!(
+            tagged.to_string(),
+            "[c] summary (!!) | b=1;;;;\n[a] details\n[b] notice\nuntagged"
+        );
+    }
+}
+
 #[cfg(test)]
 mod test_writer_format {
     use super::{CheckResult, Collection, Metric, Real, SimpleCheckResult, State};
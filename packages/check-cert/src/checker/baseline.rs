@@ -0,0 +1,210 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+//! Compare the certificate with a snapshot saved by an earlier run.
+
+use crate::check::{Collection, SimpleCheckResult};
+use crate::checker::certificate::{colon_hex, dns_names, fingerprint};
+use anyhow::{bail, Context, Result};
+use typed_builder::TypedBuilder;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
+
+/// Fields that change when the same key is certified again.
+const RENEWAL_FIELDS: &[&str] = &["serial", "not_before", "not_after", "sha256_fingerprint"];
+
+/// State of a certificate that differs from the baseline in the renewal fields only.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum Renewal {
+    #[default]
+    Accept,
+    Warn,
+}
+
+#[derive(Debug, TypedBuilder)]
+#[builder(field_defaults(default))]
+pub struct Config {
+    renewal: Renewal,
+}
+
+/// Fields of a certificate to compare, as `key: value` lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot(Vec<(String, String)>);
+
+impl Snapshot {
+    pub fn new(der: &[u8]) -> Result<Self> {
+        let (_rem, cert) = X509Certificate::from_der(der).context("Failed to parse certificate")?;
+        let alt_names = match cert.subject_alternative_name()? {
+            Some(ext) => dns_names(ext.value).collect::<Vec<_>>().join(", "),
+            None => String::new(),
+        };
+        let fields = [
+            ("subject", cert.subject().to_string()),
+            ("issuer", cert.issuer().to_string()),
+            ("serial", cert.raw_serial_as_string()),
+            ("not_before", cert.validity().not_before.to_string()),
+            ("not_after", cert.validity().not_after.to_string()),
+            ("subject_alt_names", alt_names),
+            (
+                "public_key_sha256",
+                colon_hex(&openssl::sha::sha256(cert.public_key().raw)),
+            ),
+            ("sha256_fingerprint", fingerprint(der)?),
+        ];
+        Ok(Self(
+            fields
+                .into_iter()
+                .map(|(key, value)| (String::from(key), value))
+                .collect(),
+        ))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let fields = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| match line.split_once(": ") {
+                Some((key, value)) => Ok((String::from(key), String::from(value))),
+                None => match line.strip_suffix(':') {
+                    Some(key) => Ok((String::from(key), String::new())),
+                    None => bail!("Invalid baseline line: {line}"),
+                },
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if fields.is_empty() {
+            bail!("Empty baseline")
+        }
+        Ok(Self(fields))
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find_map(|(k, v)| (k == key).then_some(v.as_str()))
+    }
+}
+
+impl std::fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (key, value) in &self.0 {
+            writeln!(f, "{key}: {value}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn check(current: &Snapshot, baseline: &Snapshot, config: Config) -> Collection {
+    Collection::from(check_baseline(current, baseline, config.renewal).tag("baseline"))
+}
+
+fn check_baseline(current: &Snapshot, baseline: &Snapshot, renewal: Renewal) -> SimpleCheckResult {
+    let changed = current
+        .0
+        .iter()
+        .filter_map(|(key, value)| {
+            let old = baseline.get(key).unwrap_or("none");
+            (old != value).then(|| (key.as_str(), format!("{key}: {old} -> {value}")))
+        })
+        .collect::<Vec<_>>();
+    if changed.is_empty() {
+        return SimpleCheckResult::notice("Baseline: unchanged");
+    }
+    let keys = changed
+        .iter()
+        .map(|(key, _)| *key)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let diff = changed
+        .iter()
+        .map(|(_, line)| line.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let renewed = changed.iter().all(|(key, _)| RENEWAL_FIELDS.contains(key));
+    match (renewed, renewal) {
+        (true, Renewal::Accept) => {
+            SimpleCheckResult::ok_with_details("Baseline: renewed with the same key", diff)
+        }
+        _ => SimpleCheckResult::warn_with_details(format!("Baseline: changed {keys}"), diff),
+    }
+}
+
+#[cfg(test)]
+mod test_check_baseline {
+    use super::{check, check_baseline, Config, Renewal, Snapshot};
+    use crate::check::{self, Collection, SimpleCheckResult};
+
+    static LEAF: &[u8] = include_bytes!("../../assets/ocsp-leaf.der");
+    static REVOKED: &[u8] = include_bytes!("../../assets/ocsp-revoked-leaf.der");
+
+    fn snapshot(fields: &[(&str, &str)]) -> Snapshot {
+        Snapshot(
+            fields
+                .iter()
+                .map(|(k, v)| (String::from(*k), String::from(*v)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let snapshot = Snapshot::new(LEAF).unwrap();
+        assert_eq!(Snapshot::parse(&snapshot.to_string()).unwrap(), snapshot);
+        assert!(snapshot
+            .to_string()
+            .starts_with("subject: CN=leaf.example.com\n"));
+        assert!(Snapshot::parse("").is_err());
+        assert!(Snapshot::parse("garbage").is_err());
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let snapshot = Snapshot::new(LEAF).unwrap();
+        assert_eq!(
+            check_baseline(&snapshot, &snapshot, Renewal::Accept),
+            SimpleCheckResult::notice("Baseline: unchanged")
+        );
+    }
+
+    #[test]
+    fn test_changed() {
+        let current = Snapshot::new(LEAF).unwrap();
+        let baseline = Snapshot::new(REVOKED).unwrap();
+        let coll = Collection::from(check_baseline(&current, &baseline, Renewal::Accept));
+        assert_eq!(check::exit_code(&coll), 1);
+        assert!(coll
+            .to_string()
+            .starts_with("Baseline: changed subject, serial, "));
+        assert!(coll
+            .to_string()
+            .contains("\nsubject: CN=revoked.example.com -> CN=leaf.example.com\n"));
+    }
+
+    #[test]
+    fn test_renewal() {
+        let baseline = snapshot(&[("subject", "CN=a"), ("serial", "01")]);
+        let current = snapshot(&[("subject", "CN=a"), ("serial", "02")]);
+        assert_eq!(
+            check_baseline(&current, &baseline, Renewal::Accept),
+            SimpleCheckResult::ok_with_details(
+                "Baseline: renewed with the same key",
+                "serial: 01 -> 02"
+            )
+        );
+        assert_eq!(
+            check_baseline(&current, &baseline, Renewal::Warn),
+            SimpleCheckResult::warn_with_details("Baseline: changed serial", "serial: 01 -> 02")
+        );
+    }
+
+    #[test]
+    fn test_tag() {
+        let current = Snapshot::new(LEAF).unwrap();
+        let baseline = Snapshot::new(REVOKED).unwrap();
+        let mut coll = check(&current, &baseline, Config::builder().build());
+        coll.show_tags();
+        assert!(coll
+            .to_string()
+            .starts_with("[baseline] Baseline: changed "));
+    }
+}
@@ -6,18 +6,28 @@ use crate::check::{
     self, CheckResult, Collection, LevelsChecker, LevelsCheckerArgs, OutputType, Real,
     SimpleCheckResult,
 };
+use crate::messages::{self, Language};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::Id;
+use openssl::x509::X509;
 use std::collections::HashSet;
 use std::convert::AsRef;
-use time::Duration;
+use std::net::IpAddr;
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime, Time, UtcOffset};
 use typed_builder::TypedBuilder;
 use x509_parser::certificate::{BasicExtension, Validity, X509Certificate};
 use x509_parser::error::X509Error;
-use x509_parser::extensions::{GeneralName, SubjectAlternativeName};
+use x509_parser::extensions::{
+    ExtendedKeyUsage, GeneralName, KeyUsage, ParsedExtension, SubjectAlternativeName,
+};
 use x509_parser::prelude::FromDer;
-use x509_parser::prelude::{oid2sn, oid_registry};
+use x509_parser::prelude::{oid2abbrev, oid2sn, oid_registry};
 use x509_parser::public_key::PublicKey;
 use x509_parser::time::ASN1Time;
-use x509_parser::x509::{AttributeTypeAndValue, SubjectPublicKeyInfo};
+use x509_parser::x509::{AttributeTypeAndValue, SubjectPublicKeyInfo, X509Name};
 
 macro_rules! unwrap_into {
     ($($e:expr),* $(,)?) => {
@@ -35,24 +45,75 @@ fn first_of<'a>(iter: &mut impl Iterator<Item = &'a AttributeTypeAndValue<'a>>)
         .unwrap_or_default()
 }
 
+/// MD2, MD5 and SHA-1 based signature algorithms.
+pub const WEAK_SIGNATURE_ALGORITHMS: [&str; 6] = [
+    "1.2.840.113549.1.1.2", // md2WithRSAEncryption
+    "1.2.840.113549.1.1.4", // md5WithRSAEncryption
+    "1.2.840.113549.1.1.5", // sha1WithRSAEncryption
+    "1.2.840.10040.4.3",    // dsaWithSHA1
+    "1.2.840.10045.4.1",    // ecdsa-with-SHA1
+    "1.3.14.3.2.29",        // sha1WithRSA (OIW)
+];
+
+/// State of a certificate that lacks some of the required subject alternative names.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum MissingSan {
+    #[default]
+    Warn,
+    Crit,
+}
+
+/// State of a certificate without extended key usage, that is, valid for any purpose.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum MissingEku {
+    #[default]
+    Any,
+    Warn,
+    Crit,
+}
+
 #[derive(Debug, TypedBuilder)]
 #[builder(field_defaults(default))]
 pub struct Config {
     pubkey_algorithm: Option<String>,
     pubkey_size: Option<usize>,
+    /// Levels on the RSA size or, for other keys, on the comparable RSA size.
+    pubkey_bits: Option<LevelsChecker<u32>>,
+    sct_count: Option<LevelsChecker<u32>>,
     serial: Option<String>,
+    expected_fingerprint: Option<String>,
     signature_algorithm: Option<String>,
+    forbidden_signature_algorithms: Option<Vec<String>>,
     subject_cn: Option<String>,
     subject_alt_names: Option<Vec<String>>,
+    hostname: Option<String>,
+    /// Name sent with SNI, warn if the server answered with a certificate for another name.
+    sni: Option<String>,
+    required_sans: Option<Vec<String>>,
+    missing_san: MissingSan,
+    /// Labels that must follow the wildcard of a SAN, `*.example.com` has two.
+    /// Only the labels are counted, a public suffix like `co.uk` is not known.
+    #[builder(default = Some(2))]
+    wildcard_min_labels: Option<usize>,
+    required_eku: Option<Vec<String>>,
+    missing_eku: MissingEku,
     subject_o: Option<String>,
     subject_ou: Option<String>,
     issuer_cn: Option<String>,
+    expected_issuer: Option<String>,
     issuer_o: Option<String>,
     issuer_ou: Option<String>,
     issuer_st: Option<String>,
     issuer_c: Option<String>,
     not_after: Option<LevelsChecker<Duration>>,
-    max_validity: Option<Duration>,
+    /// Fixed dates before which the certificate must not expire, on top of `not_after`.
+    warn_deadline: Option<OffsetDateTime>,
+    crit_deadline: Option<OffsetDateTime>,
+    #[builder(default = Duration::minutes(5))]
+    not_before_skew: Duration,
+    cert_age: Option<LevelsChecker<Duration>>,
+    max_validity: Option<LevelsChecker<Duration>>,
+    language: Language,
 }
 
 fn handle_empty(s: &str) -> &str {
@@ -63,7 +124,7 @@ fn handle_empty(s: &str) -> &str {
     }
 }
 
-fn format_oid(oid: &oid_registry::Oid) -> String {
+pub(crate) fn format_oid(oid: &oid_registry::Oid) -> String {
     match oid2sn(oid, oid_registry()) {
         Ok(s) => s.to_owned(),
         _ => format!("{oid}"),
@@ -80,106 +141,182 @@ pub fn check(der: &[u8], config: Config) -> Collection {
     let issuer_cn = first_of(&mut cert.issuer().iter_common_name());
 
     Collection::from(&mut unwrap_into!(
-        Some(check_subject_cn(subject_cn, config.subject_cn)),
+        Some(check_subject_cn(subject_cn, config.subject_cn).tag("subject")),
         Some(SimpleCheckResult::notice(format!(
             "Subject CN: {subject_cn}"
         ))),
-        check_subject_alt_names(cert.subject_alternative_name(), config.subject_alt_names),
-        config.subject_o.map(|expected| {
-            let name = "Subject O";
-            let value = first_of(&mut cert.subject().iter_organization());
-            if expected == value {
-                SimpleCheckResult::notice(format!("{name}: {value}"))
-            } else {
-                SimpleCheckResult::warn(format!(
-                    "{name}: {} but expected {expected}",
-                    handle_empty(value),
-                ))
-            }
-        }),
-        config.subject_ou.map(|expected| {
-            let name = "Subject OU";
-            let value = first_of(&mut cert.subject().iter_organizational_unit());
-            if expected == value {
-                SimpleCheckResult::notice(format!("{name}: {value}"))
-            } else {
-                SimpleCheckResult::warn(format!(
-                    "{name}: {} but expected {expected}",
-                    handle_empty(value),
-                ))
-            }
-        }),
-        check_serial(cert.raw_serial_as_string(), config.serial),
-        Some(check_issuer_cn(issuer_cn, config.issuer_cn)),
-        config.issuer_o.map(|expected| {
-            let name = "Issuer O";
-            let value = first_of(&mut cert.issuer().iter_organization());
-            if expected == value {
-                SimpleCheckResult::notice(format!("{name}: {value}"))
-            } else {
-                SimpleCheckResult::warn(format!(
-                    "{name}: {} but expected {expected}",
-                    handle_empty(value),
-                ))
-            }
-        }),
-        config.issuer_ou.map(|expected| {
-            let name = "Issuer OU";
-            let value = first_of(&mut cert.issuer().iter_organizational_unit());
-            if expected == value {
-                SimpleCheckResult::notice(format!("{name}: {value}"))
-            } else {
-                SimpleCheckResult::warn(format!(
-                    "{name}: {} but expected {expected}",
-                    handle_empty(value),
-                ))
-            }
-        }),
-        config.issuer_st.map(|expected| {
-            let name = "Issuer ST";
-            let value = first_of(&mut cert.issuer().iter_state_or_province());
-            if expected == value {
-                SimpleCheckResult::notice(format!("{name}: {value}"))
-            } else {
-                SimpleCheckResult::warn(format!(
-                    "{name}: {} but expected {expected}",
-                    handle_empty(value),
-                ))
-            }
-        }),
-        config.issuer_c.map(|expected| {
-            let name = "Issuer C";
-            let value = first_of(&mut cert.issuer().iter_country());
-            if expected == value {
-                SimpleCheckResult::notice(format!("{name}: {value}"))
-            } else {
-                SimpleCheckResult::warn(format!(
-                    "{name}: {} but expected {expected}",
-                    handle_empty(value),
-                ))
-            }
-        }),
-        config.signature_algorithm.map(|expected| {
-            let name = "Certificate signature algorithm";
-            let value = &cert.signature_algorithm.algorithm;
-            if expected == value.to_string() {
-                SimpleCheckResult::notice(format!("{name}: {}", format_oid(value)))
-            } else {
-                SimpleCheckResult::warn(format!(
-                    "{name}: {} ({value}) but expected {expected}",
-                    handle_empty(&format_oid(value)),
-                ))
-            }
-        }),
-        check_pubkey_algorithm(cert.public_key(), config.pubkey_algorithm),
-        check_pubkey_size(cert.public_key(), config.pubkey_size),
-        check_validity_not_after(
-            cert.validity().time_to_expiration(),
-            config.not_after,
+        check_subject_alt_names(cert.subject_alternative_name(), config.subject_alt_names)
+            .map(|r| r.tag("san")),
+        check_hostname(cert.subject_alternative_name(), config.hostname).map(|r| r.tag("hostname")),
+        check_default_cert(&cert, config.sni).map(|r| r.tag("default_cert")),
+        check_required_sans(
+            cert.subject_alternative_name(),
+            config.required_sans,
+            config.missing_san
+        )
+        .map(|r| r.tag("san")),
+        check_wildcards(cert.subject_alternative_name(), config.wildcard_min_labels)
+            .map(|r| r.tag("wildcard")),
+        check_key_usage(
+            cert.key_usage(),
+            cert.extended_key_usage(),
+            config.required_eku,
+            config.missing_eku
+        )
+        .map(|r| r.tag("eku")),
+        config
+            .subject_o
+            .map(|expected| {
+                let name = "Subject O";
+                let value = first_of(&mut cert.subject().iter_organization());
+                if expected == value {
+                    SimpleCheckResult::notice(format!("{name}: {value}"))
+                } else {
+                    SimpleCheckResult::warn(format!(
+                        "{name}: {} but expected {expected}",
+                        handle_empty(value),
+                    ))
+                }
+            })
+            .map(|r| r.tag("subject")),
+        config
+            .subject_ou
+            .map(|expected| {
+                let name = "Subject OU";
+                let value = first_of(&mut cert.subject().iter_organizational_unit());
+                if expected == value {
+                    SimpleCheckResult::notice(format!("{name}: {value}"))
+                } else {
+                    SimpleCheckResult::warn(format!(
+                        "{name}: {} but expected {expected}",
+                        handle_empty(value),
+                    ))
+                }
+            })
+            .map(|r| r.tag("subject")),
+        check_serial(cert.raw_serial_as_string(), config.serial).map(|r| r.tag("serial")),
+        check_fingerprint(der, config.expected_fingerprint).map(|r| r.tag("fingerprint")),
+        Some(check_issuer_cn(issuer_cn, config.issuer_cn).tag("issuer")),
+        check_issuer(cert.issuer(), config.expected_issuer).map(|r| r.tag("issuer")),
+        config
+            .issuer_o
+            .map(|expected| {
+                let name = "Issuer O";
+                let value = first_of(&mut cert.issuer().iter_organization());
+                if expected == value {
+                    SimpleCheckResult::notice(format!("{name}: {value}"))
+                } else {
+                    SimpleCheckResult::warn(format!(
+                        "{name}: {} but expected {expected}",
+                        handle_empty(value),
+                    ))
+                }
+            })
+            .map(|r| r.tag("issuer")),
+        config
+            .issuer_ou
+            .map(|expected| {
+                let name = "Issuer OU";
+                let value = first_of(&mut cert.issuer().iter_organizational_unit());
+                if expected == value {
+                    SimpleCheckResult::notice(format!("{name}: {value}"))
+                } else {
+                    SimpleCheckResult::warn(format!(
+                        "{name}: {} but expected {expected}",
+                        handle_empty(value),
+                    ))
+                }
+            })
+            .map(|r| r.tag("issuer")),
+        config
+            .issuer_st
+            .map(|expected| {
+                let name = "Issuer ST";
+                let value = first_of(&mut cert.issuer().iter_state_or_province());
+                if expected == value {
+                    SimpleCheckResult::notice(format!("{name}: {value}"))
+                } else {
+                    SimpleCheckResult::warn(format!(
+                        "{name}: {} but expected {expected}",
+                        handle_empty(value),
+                    ))
+                }
+            })
+            .map(|r| r.tag("issuer")),
+        config
+            .issuer_c
+            .map(|expected| {
+                let name = "Issuer C";
+                let value = first_of(&mut cert.issuer().iter_country());
+                if expected == value {
+                    SimpleCheckResult::notice(format!("{name}: {value}"))
+                } else {
+                    SimpleCheckResult::warn(format!(
+                        "{name}: {} but expected {expected}",
+                        handle_empty(value),
+                    ))
+                }
+            })
+            .map(|r| r.tag("issuer")),
+        config
+            .signature_algorithm
+            .map(|expected| {
+                let name = "Certificate signature algorithm";
+                let value = &cert.signature_algorithm.algorithm;
+                if expected == value.to_string() {
+                    SimpleCheckResult::notice(format!("{name}: {}", format_oid(value)))
+                } else {
+                    SimpleCheckResult::warn(format!(
+                        "{name}: {} ({value}) but expected {expected}",
+                        handle_empty(&format_oid(value)),
+                    ))
+                }
+            })
+            .map(|r| r.tag("signature_algo")),
+        check_signature_algorithm(
+            &cert.signature_algorithm.algorithm,
+            config.forbidden_signature_algorithms
+        )
+        .map(|r| r.tag("signature_algo")),
+        check_pubkey_algorithm(cert.public_key(), config.pubkey_algorithm).map(|r| r.tag("pubkey")),
+        check_pubkey_size(cert.public_key(), config.pubkey_size).map(|r| r.tag("pubkey")),
+        check_pubkey_bits(der, cert.public_key(), config.pubkey_bits)
+            .map(|cr: CheckResult<u32>| cr.map(|x| Real::from(x as isize)).tag("pubkey")),
+        check_sct_count(&cert, config.sct_count)
+            .map(|cr: CheckResult<u32>| cr.map(|x| Real::from(x as isize)).tag("sct")),
+        Some(
+            check_validity(
+                cert.validity().not_after,
+                ASN1Time::now(),
+                config.not_after,
+                messages::subject_issuer(config.language, cert.subject(), cert.issuer()),
+                config.language,
+            )
+            .map(|x| Real::from(x.whole_seconds() as isize))
+            .tag("expiry")
+        ),
+        check_deadline(
             cert.validity().not_after,
+            config.warn_deadline,
+            config.crit_deadline
+        )
+        .map(|r| r.tag("expiry")),
+        check_not_before(
+            cert.validity().not_before,
+            ASN1Time::now(),
+            config.not_before_skew
         )
-        .map(|cr: CheckResult<Duration>| cr.map(|x| Real::from(x.whole_seconds() as isize))),
-        check_max_validity(cert.validity(), config.max_validity),
+        .map(|r| r.tag("not_before")),
+        Some(
+            check_age(cert.validity().not_before, ASN1Time::now(), config.cert_age)
+                .map(|x| Real::from(x.whole_seconds() as isize))
+                .tag("age")
+        ),
+        Some(
+            check_max_validity(cert.validity(), config.max_validity)
+                .map(|x| Real::from(x.whole_seconds() as isize))
+                .tag("lifetime")
+        ),
     ))
 }
 
@@ -194,6 +331,40 @@ fn check_serial(serial: String, expected: Option<String>) -> Option<SimpleCheckR
     })
 }
 
+pub(crate) fn colon_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// SHA-256 fingerprint as colon separated upper case hex.
+pub(crate) fn fingerprint(der: &[u8]) -> Result<String, ErrorStack> {
+    Ok(colon_hex(
+        &X509::from_der(der)?.digest(MessageDigest::sha256())?,
+    ))
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint.replace(':', "").to_lowercase()
+}
+
+fn check_fingerprint(der: &[u8], expected: Option<String>) -> Option<SimpleCheckResult> {
+    expected.map(|expected| {
+        let Ok(value) = fingerprint(der) else {
+            return SimpleCheckResult::unknown("Failed to compute fingerprint");
+        };
+        if normalize_fingerprint(&value) == normalize_fingerprint(&expected) {
+            SimpleCheckResult::notice(format!("Fingerprint (SHA-256): {value}"))
+        } else {
+            SimpleCheckResult::crit(format!(
+                "Fingerprint mismatch: {value} but expected {expected}"
+            ))
+        }
+    })
+}
+
 fn check_subject_cn(subject_cn: &str, expected: Option<String>) -> SimpleCheckResult {
     let name = "Subject CN";
     expected.map_or(
@@ -229,6 +400,83 @@ fn check_issuer_cn(issuer_cn: &str, expected: Option<String>) -> SimpleCheckResu
     })
 }
 
+/// Attributes in a canonical order for comparison.
+///
+/// Attribute types are upper cased, values are compared case-insensitively, with trimmed
+/// and collapsed whitespace as in the caseIgnoreMatch of RFC 4518.
+fn normalize_attributes(
+    attributes: impl Iterator<Item = (String, String)>,
+) -> Vec<(String, String)> {
+    let mut attributes = attributes
+        .map(|(key, value)| {
+            (
+                key.trim().to_uppercase(),
+                value
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .to_lowercase(),
+            )
+        })
+        .collect::<Vec<_>>();
+    attributes.sort();
+    attributes
+}
+
+/// The attributes of a parsed name, multi-valued RDNs and commas in values included.
+fn name_attributes(name: &X509Name) -> Vec<(String, String)> {
+    normalize_attributes(name.iter_attributes().map(|attribute| {
+        (
+            oid2abbrev(attribute.attr_type(), oid_registry())
+                .map_or_else(|_| attribute.attr_type().to_id_string(), String::from),
+            attribute.as_str().unwrap_or_default().to_string(),
+        )
+    }))
+}
+
+/// Attributes of a `C=US, O=Org` (or OpenSSL `/C=US/O=Org`) DN in a canonical order.
+fn normalize_dn(dn: &str) -> Vec<(String, String)> {
+    let dn = dn.trim();
+    let (dn, separator) = match dn.strip_prefix('/') {
+        Some(dn) => (dn, '/'),
+        None => (dn, ','),
+    };
+    let mut attributes = Vec::new();
+    let mut current = String::new();
+    let mut chars = dn.chars();
+    // Split on unescaped separators only, `O=Foo\, Inc.` is one attribute.
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            c if c == separator => attributes.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    attributes.push(current);
+    normalize_attributes(
+        attributes
+            .iter()
+            .filter_map(|attribute| attribute.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string())),
+    )
+}
+
+fn check_issuer(issuer: &X509Name, expected: Option<String>) -> Option<SimpleCheckResult> {
+    expected.map(|expected| {
+        let name = "Issuer DN";
+        let actual = name_attributes(issuer);
+        let issuer = issuer.to_string();
+        if actual == normalize_dn(&expected) {
+            SimpleCheckResult::notice(format!("{name}: {issuer}"))
+        } else {
+            SimpleCheckResult::warn(format!(
+                "{name}: {} but expected {expected}",
+                handle_empty(&issuer)
+            ))
+        }
+    })
+}
+
 fn check_subject_alt_names(
     alt_names: Result<Option<BasicExtension<&SubjectAlternativeName<'_>>>, X509Error>,
     expected: Option<Vec<String>>,
@@ -244,12 +492,7 @@ fn check_subject_alt_names(
             }
         }
         Ok(Some(ext)) => {
-            let found = HashSet::<&str>::from_iter(ext.value.general_names.iter().flat_map(
-                |name| match name {
-                    GeneralName::DNSName(v) => Some(*v),
-                    _ => None,
-                },
-            ));
+            let found = HashSet::<&str>::from_iter(dns_names(ext.value));
             let expected_set = HashSet::from_iter(expected.iter().map(AsRef::as_ref));
             if found.is_superset(&expected_set) {
                 SimpleCheckResult::notice(format!("{name}: {}", expected.join(", ")))
@@ -267,20 +510,330 @@ fn check_subject_alt_names(
     })
 }
 
+fn check_signature_algorithm(
+    signature_algorithm: &oid_registry::Oid,
+    forbidden: Option<Vec<String>>,
+) -> Option<SimpleCheckResult> {
+    forbidden.map(|forbidden| {
+        let oid = signature_algorithm.to_string();
+        let value = format_oid(signature_algorithm);
+        if forbidden
+            .iter()
+            .any(|f| *f == oid || f.eq_ignore_ascii_case(&value))
+        {
+            SimpleCheckResult::crit(format!("Weak signature algorithm: {value}"))
+        } else {
+            SimpleCheckResult::ok(format!("Signature algorithm: {value}"))
+        }
+    })
+}
+
+pub(crate) fn matches_hostname(pattern: &str, hostname: &str) -> bool {
+    // Wildcard matching as in RFC 6125, section 6.4.3: the wildcard may only
+    // appear in the left-most label and matches exactly one label.
+    let pattern = pattern.trim_end_matches('.').to_lowercase();
+    let hostname = hostname.trim_end_matches('.').to_lowercase();
+    let (Some((first, pattern_rest)), Some((label, hostname_rest))) =
+        (pattern.split_once('.'), hostname.split_once('.'))
+    else {
+        return !pattern.contains('*') && pattern == hostname;
+    };
+    if pattern_rest.contains('*') || pattern_rest != hostname_rest {
+        return false;
+    }
+    match first.split_once('*') {
+        None => first == label,
+        Some((_, suffix)) if suffix.contains('*') => false,
+        Some((prefix, suffix)) => {
+            label.len() >= prefix.len() + suffix.len()
+                && label.starts_with(prefix)
+                && label.ends_with(suffix)
+        }
+    }
+}
+
+/// An IP literal, in brackets or not.
+fn ip_literal(hostname: &str) -> Option<IpAddr> {
+    hostname
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(hostname)
+        .parse()
+        .ok()
+}
+
+/// The SAN that covers the hostname: an IP address for an IP literal and
+/// a DNS name otherwise, never one for the other (RFC 6125, section 6.2).
+fn matching_san(alt_names: &SubjectAlternativeName<'_>, hostname: &str) -> Option<String> {
+    let Some(ip) = ip_literal(hostname) else {
+        return dns_names(alt_names)
+            .find(|name| matches_hostname(name, hostname))
+            .map(String::from);
+    };
+    alt_names
+        .general_names
+        .iter()
+        .filter_map(|name| match name {
+            GeneralName::IPAddress(bytes) => match bytes.len() {
+                4 => <[u8; 4]>::try_from(*bytes).ok().map(IpAddr::from),
+                16 => <[u8; 16]>::try_from(*bytes).ok().map(IpAddr::from),
+                _ => None,
+            },
+            _ => None,
+        })
+        .find(|addr| *addr == ip)
+        .map(|addr| addr.to_string())
+}
+
+/// The SANs cover the hostname, or the subject CN without SAN extension.
+pub(crate) fn covers_hostname(cert: &X509Certificate, hostname: &str) -> bool {
+    match cert.subject_alternative_name() {
+        Ok(Some(ext)) => matching_san(ext.value, hostname).is_some(),
+        _ => cert
+            .subject()
+            .iter_common_name()
+            .filter_map(|cn| cn.as_str().ok())
+            .any(|cn| matches_hostname(cn, hostname)),
+    }
+}
+
+fn check_default_cert(cert: &X509Certificate, sni: Option<String>) -> Option<SimpleCheckResult> {
+    sni.map(|sni| {
+        if covers_hostname(cert, &sni) {
+            return SimpleCheckResult::notice(format!("Certificate matches SNI name {sni}"));
+        }
+        let alt_names = match cert.subject_alternative_name() {
+            Ok(Some(ext)) => dns_names(ext.value).collect::<Vec<_>>().join(", "),
+            _ => String::from("none"),
+        };
+        SimpleCheckResult::warn(format!(
+            "Certificate for {} (SAN: {alt_names}) does not match SNI name {sni}, \
+            likely the default certificate of the server",
+            cert.subject()
+        ))
+    })
+}
+
+pub(crate) fn dns_names<'a>(
+    alt_names: &'a SubjectAlternativeName<'_>,
+) -> impl Iterator<Item = &'a str> {
+    alt_names.general_names.iter().flat_map(|name| match name {
+        GeneralName::DNSName(v) => Some(*v),
+        _ => None,
+    })
+}
+
+fn check_hostname(
+    alt_names: Result<Option<BasicExtension<&SubjectAlternativeName<'_>>>, X509Error>,
+    hostname: Option<String>,
+) -> Option<SimpleCheckResult> {
+    hostname.map(|hostname| match alt_names {
+        Err(err) => SimpleCheckResult::crit(format!("Subject alternative names: {err}")),
+        Ok(None) => SimpleCheckResult::crit(format!("Hostname {hostname} not in SAN list")),
+        Ok(Some(ext)) => match matching_san(ext.value, &hostname) {
+            Some(name) => SimpleCheckResult::notice(format!("Hostname {hostname} matches {name}")),
+            None => SimpleCheckResult::crit(format!("Hostname {hostname} not in SAN list")),
+        },
+    })
+}
+
+fn check_required_sans(
+    alt_names: Result<Option<BasicExtension<&SubjectAlternativeName<'_>>>, X509Error>,
+    required: Option<Vec<String>>,
+    missing_san: MissingSan,
+) -> Option<SimpleCheckResult> {
+    let name = "Required subject alternative names";
+    let missing = |text: String| match missing_san {
+        MissingSan::Warn => SimpleCheckResult::warn(text),
+        MissingSan::Crit => SimpleCheckResult::crit(text),
+    };
+    required.map(|required| match alt_names {
+        Err(err) => SimpleCheckResult::crit(format!("{name}: {err}")),
+        Ok(None) => missing(format!("{name}: missing all, no SAN extension")),
+        Ok(Some(ext)) => {
+            let absent = required
+                .iter()
+                .filter(|req| matching_san(ext.value, req).is_none())
+                .map(|req| format!(r#""{req}""#))
+                .collect::<Vec<_>>();
+            if absent.is_empty() {
+                SimpleCheckResult::notice(format!("{name}: {}", required.join(", ")))
+            } else {
+                missing(format!("{name}: missing {}", absent.join(", ")))
+            }
+        }
+    })
+}
+
+/// Labels after the wildcard, none if the wildcard is not in the left-most label.
+fn wildcard_labels(name: &str) -> Option<usize> {
+    let name = name.trim_end_matches('.');
+    match name.split_once('.') {
+        None => Some(0),
+        Some((_, rest)) if rest.contains('*') => None,
+        Some((_, rest)) => Some(rest.split('.').count()),
+    }
+}
+
+fn check_wildcards(
+    alt_names: Result<Option<BasicExtension<&SubjectAlternativeName<'_>>>, X509Error>,
+    min_labels: Option<usize>,
+) -> Option<SimpleCheckResult> {
+    let min_labels = min_labels?;
+    let Ok(Some(ext)) = alt_names else {
+        return None;
+    };
+    let too_broad = dns_names(ext.value)
+        .filter(|name| name.contains('*'))
+        .map(|name| (name, wildcard_labels(name)))
+        .filter(|(_, labels)| labels.map_or(true, |labels| labels < min_labels))
+        .collect::<Vec<_>>();
+    if too_broad.is_empty() {
+        return None;
+    }
+    let text = format!(
+        "Wildcard subject alternative names too broad: {} (expected at least {min_labels} label(s) after the wildcard)",
+        too_broad
+            .iter()
+            .map(|(name, _)| format!(r#""{name}""#))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    // `*`, `*.tld` and misplaced wildcards are never legitimate.
+    if too_broad
+        .iter()
+        .any(|(_, labels)| labels.map_or(true, |labels| labels < 2))
+    {
+        Some(SimpleCheckResult::crit(text))
+    } else {
+        Some(SimpleCheckResult::warn(text))
+    }
+}
+
+/// OIDs of the extended key usages x509-parser knows by name (RFC 5280, 4.2.1.12).
+const EKU_OIDS: [(&str, &str); 7] = [
+    ("2.5.29.37.0", "anyExtendedKeyUsage"),
+    ("1.3.6.1.5.5.7.3.1", "serverAuth"),
+    ("1.3.6.1.5.5.7.3.2", "clientAuth"),
+    ("1.3.6.1.5.5.7.3.3", "codeSigning"),
+    ("1.3.6.1.5.5.7.3.4", "emailProtection"),
+    ("1.3.6.1.5.5.7.3.8", "timeStamping"),
+    ("1.3.6.1.5.5.7.3.9", "OCSPSigning"),
+];
+
+/// The name of a known extended key usage given by OID, anything else as is.
+fn eku_name(eku: &str) -> String {
+    EKU_OIDS
+        .iter()
+        .find(|(oid, _)| *oid == eku)
+        .map_or_else(|| eku.to_string(), |(_, name)| name.to_string())
+}
+
+fn eku_names(eku: &ExtendedKeyUsage) -> Vec<String> {
+    [
+        (eku.any, "anyExtendedKeyUsage"),
+        (eku.server_auth, "serverAuth"),
+        (eku.client_auth, "clientAuth"),
+        (eku.code_signing, "codeSigning"),
+        (eku.email_protection, "emailProtection"),
+        (eku.time_stamping, "timeStamping"),
+        (eku.ocsp_signing, "OCSPSigning"),
+    ]
+    .into_iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, name)| String::from(name))
+    .chain(eku.other.iter().map(ToString::to_string))
+    .collect()
+}
+
+fn check_key_usage(
+    key_usage: Result<Option<BasicExtension<&KeyUsage>>, X509Error>,
+    eku: Result<Option<BasicExtension<&ExtendedKeyUsage>>, X509Error>,
+    required: Option<Vec<String>>,
+    missing_eku: MissingEku,
+) -> Option<SimpleCheckResult> {
+    let name = "Extended key usage";
+    required.map(|required| {
+        let eku = match eku {
+            Err(err) => return SimpleCheckResult::crit(format!("{name}: {err}")),
+            Ok(None) => {
+                let text = format!("No {} (any)", name.to_lowercase());
+                return match missing_eku {
+                    MissingEku::Any => SimpleCheckResult::ok(text),
+                    MissingEku::Warn => SimpleCheckResult::warn(text),
+                    MissingEku::Crit => SimpleCheckResult::crit(text),
+                };
+            }
+            Ok(Some(ext)) => ext.value,
+        };
+        let required = required.iter().map(|r| eku_name(r)).collect::<Vec<_>>();
+        let found = eku_names(eku);
+        let missing = required
+            .iter()
+            .filter(|r| !eku.any && !found.iter().any(|f| f.eq_ignore_ascii_case(r)))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        // RFC 5246, section 7.4.2: the key must sign or encipher for the handshake.
+        let unsuitable = match key_usage {
+            Ok(Some(ku))
+                if required
+                    .iter()
+                    .any(|r| r.eq_ignore_ascii_case("serverAuth"))
+                    && !(ku.value.digital_signature()
+                        || ku.value.key_encipherment()
+                        || ku.value.key_agreement()) =>
+            {
+                Some(ku.value)
+            }
+            _ => None,
+        };
+        let found = handle_empty(&found.join(", ")).to_string();
+        if !missing.is_empty() {
+            SimpleCheckResult::warn(format!(
+                "{name}: {found} but expected {}",
+                missing.join(", ")
+            ))
+        } else if let Some(ku) = unsuitable {
+            SimpleCheckResult::warn(format!(
+                "{name}: {found} but key usage {ku} does not allow serverAuth"
+            ))
+        } else {
+            SimpleCheckResult::ok(format!("{name}: {found}"))
+        }
+    })
+}
+
+pub(crate) fn pubkey_algorithm(pubkey: &SubjectPublicKeyInfo) -> Result<&'static str, X509Error> {
+    Ok(match pubkey.parsed()? {
+        PublicKey::RSA(_) => "RSA",
+        PublicKey::EC(_) => "EC",
+        PublicKey::DSA(_) => "DSA",
+        PublicKey::GostR3410(_) => "GostR3410",
+        PublicKey::GostR3410_2012(_) => "GostR3410_2012",
+        PublicKey::Unknown(_) => "Unknown",
+    })
+}
+
+pub(crate) fn pubkey_size(pubkey: &SubjectPublicKeyInfo) -> Result<usize, X509Error> {
+    Ok(match pubkey.parsed()? {
+        // more or less stolen from upstream `examples/print-cert.rs`.
+        PublicKey::RSA(rsa) => rsa.key_size(),
+        PublicKey::EC(ec) => ec.key_size(),
+        PublicKey::DSA(k)
+        | PublicKey::GostR3410(k)
+        | PublicKey::GostR3410_2012(k)
+        | PublicKey::Unknown(k) => 8 * k.len(),
+    })
+}
+
 fn check_pubkey_algorithm(
     pubkey: &SubjectPublicKeyInfo,
     expected: Option<String>,
 ) -> Option<SimpleCheckResult> {
     expected.map(|expected| {
         let name = "Public key algorithm";
-        let value = match pubkey.parsed() {
-            Ok(PublicKey::RSA(_)) => "RSA",
-            Ok(PublicKey::EC(_)) => "EC",
-            Ok(PublicKey::DSA(_)) => "DSA",
-            Ok(PublicKey::GostR3410(_)) => "GostR3410",
-            Ok(PublicKey::GostR3410_2012(_)) => "GostR3410_2012",
-            Ok(PublicKey::Unknown(_)) => "Unknown",
-            Err(_) => return SimpleCheckResult::warn("Invalid public key"),
+        let Ok(value) = pubkey_algorithm(pubkey) else {
+            return SimpleCheckResult::warn("Invalid public key");
         };
         if expected == value {
             SimpleCheckResult::notice(format!("{name}: {value}"))
@@ -296,15 +849,8 @@ fn check_pubkey_size(
 ) -> Option<SimpleCheckResult> {
     expected.map(|expected| {
         let name = "Public key size";
-        let value = match pubkey.parsed() {
-            // more or less stolen from upstream `examples/print-cert.rs`.
-            Ok(PublicKey::RSA(rsa)) => rsa.key_size(),
-            Ok(PublicKey::EC(ec)) => ec.key_size(),
-            Ok(PublicKey::DSA(k))
-            | Ok(PublicKey::GostR3410(k))
-            | Ok(PublicKey::GostR3410_2012(k))
-            | Ok(PublicKey::Unknown(k)) => 8 * k.len(),
-            Err(_) => return SimpleCheckResult::warn("Invalid public key"),
+        let Ok(value) = pubkey_size(pubkey) else {
+            return SimpleCheckResult::warn("Invalid public key");
         };
         if expected == value {
             SimpleCheckResult::notice(format!("{name}: {value}"))
@@ -314,51 +860,252 @@ fn check_pubkey_size(
     })
 }
 
-fn check_validity_not_after(
-    time_to_expiration: Option<Duration>,
-    levels: Option<LevelsChecker<Duration>>,
-    not_after: ASN1Time,
-) -> Option<CheckResult<Duration>> {
-    levels.map(|levels| match time_to_expiration {
-        None => SimpleCheckResult::crit(format!("Certificate expired ({not_after})")).into(),
-        Some(time_to_expiration) => levels.check(
-            time_to_expiration,
-            OutputType::Summary(format!(
-                "Remaining validity: {} day(s) ({})",
-                time_to_expiration.whole_days(),
-                not_after
+/// RSA size of the same security strength, from NIST SP 800-57 Part 1, Table 2.
+/// Strengths between the rows round down: Ed448 with 224 bits counts as RSA 7680.
+fn rsa_equivalent_bits(security_bits: u32) -> u32 {
+    match security_bits {
+        0..=111 => 1024,
+        112..=127 => 2048,
+        128..=191 => 3072,
+        192..=255 => 7680,
+        _ => 15360,
+    }
+}
+
+fn curve_name(nid: Nid) -> String {
+    match nid {
+        Nid::X9_62_PRIME256V1 => String::from("P-256"),
+        Nid::SECP384R1 => String::from("P-384"),
+        Nid::SECP521R1 => String::from("P-521"),
+        nid => nid
+            .short_name()
+            .map_or(String::from("unknown curve"), String::from),
+    }
+}
+
+/// RSA and DSA keys by their size, the others by the RSA size of their
+/// security strength so that the same levels apply to all key types.
+fn check_pubkey_bits(
+    der: &[u8],
+    pubkey: &SubjectPublicKeyInfo,
+    levels: Option<LevelsChecker<u32>>,
+) -> Option<CheckResult<u32>> {
+    levels.map(|levels| {
+        let Ok(key) = X509::from_der(der).and_then(|cert| cert.public_key()) else {
+            return SimpleCheckResult::unknown("Invalid public key").into();
+        };
+        let name = match key.id() {
+            Id::RSA | Id::DSA => {
+                let bits = key.bits();
+                let algorithm = if key.id() == Id::RSA { "RSA" } else { "DSA" };
+                return levels.check(
+                    bits,
+                    OutputType::Notice(format!("Public key size: {bits} bits ({algorithm})")),
+                    pubkey_bits_args(),
+                );
+            }
+            Id::EC => match key.ec_key().map(|ec| ec.group().curve_name()) {
+                Ok(Some(nid)) => format!("EC {}", curve_name(nid)),
+                _ => format!("EC {} bits", key.bits()),
+            },
+            Id::ED25519 => String::from("Ed25519"),
+            Id::ED448 => String::from("Ed448"),
+            _ => {
+                return SimpleCheckResult::unknown(format!(
+                    "Public key size: unsupported key type ({})",
+                    format_oid(&pubkey.algorithm.algorithm)
+                ))
+                .into()
+            }
+        };
+        let security_bits = key.security_bits();
+        let equivalent = rsa_equivalent_bits(security_bits);
+        levels.check(
+            equivalent,
+            OutputType::Notice(format!(
+                "Public key size: {name} ({security_bits} security bits, \
+                comparable to {equivalent} bits RSA)"
             )),
-            LevelsCheckerArgs::builder()
-                .label("certificate_remaining_validity")
-                .uom("s".parse().unwrap())
-                .build(),
-        ),
+            pubkey_bits_args(),
+        )
     })
 }
 
-fn check_max_validity(
-    validity: &Validity,
-    max_validity: Option<Duration>,
+fn pubkey_bits_args() -> LevelsCheckerArgs {
+    LevelsCheckerArgs::builder()
+        .label("pubkey_bits")
+        .uom("bits".parse().unwrap())
+        .build()
+}
+
+fn check_sct_count(
+    cert: &X509Certificate,
+    levels: Option<LevelsChecker<u32>>,
+) -> Option<CheckResult<u32>> {
+    levels.map(|levels| {
+        // Only counted, the signatures of the logs are not verified.
+        let scts = cert
+            .extensions()
+            .iter()
+            .filter_map(|ext| match ext.parsed_extension() {
+                ParsedExtension::SCT(scts) => Some(scts),
+                _ => None,
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        let logs = scts
+            .iter()
+            .map(|sct| {
+                let timestamp = ASN1Time::from_timestamp((sct.timestamp / 1000) as i64)
+                    .map_or(String::from("invalid timestamp"), |t| t.to_string());
+                format!("SCT from log {} ({timestamp})", colon_hex(sct.id.key_id))
+            })
+            .collect::<Vec<_>>();
+        levels.check(
+            scts.len() as u32,
+            OutputType::Details(
+                format!("Signed certificate timestamps: {}", scts.len()),
+                logs.join("\n"),
+            ),
+            LevelsCheckerArgs::builder().label("sct_count").build(),
+        )
+    })
+}
+
+fn check_validity(
+    not_after: ASN1Time,
+    now: ASN1Time,
+    levels: Option<LevelsChecker<Duration>>,
+    details: String,
+    lang: Language,
+) -> CheckResult<Duration> {
+    // `ASN1Time` subtraction is unsigned, go through the (signed) datetimes.
+    let time_to_expiration = not_after.to_datetime() - now.to_datetime();
+    check::check_levels(
+        time_to_expiration,
+        levels,
+        OutputType::Details(
+            if time_to_expiration.is_negative() {
+                messages::expired_ago(lang, time_to_expiration.abs().whole_days(), not_after)
+            } else {
+                messages::expires_in(lang, time_to_expiration.whole_days(), not_after)
+            },
+            details,
+        ),
+        LevelsCheckerArgs::builder()
+            .label("certificate_remaining_validity")
+            .uom("s".parse().unwrap())
+            .build(),
+    )
+}
+
+fn format_deadline(deadline: OffsetDateTime) -> String {
+    let deadline = deadline.to_offset(UtcOffset::UTC);
+    if deadline.time() == Time::MIDNIGHT {
+        deadline.date().to_string()
+    } else {
+        deadline
+            .format(&Rfc3339)
+            .unwrap_or_else(|_| deadline.to_string())
+    }
+}
+
+fn check_deadline(
+    not_after: ASN1Time,
+    warn: Option<OffsetDateTime>,
+    crit: Option<OffsetDateTime>,
 ) -> Option<SimpleCheckResult> {
-    max_validity.map(|max_validity| {
-        if let Some(total_validity) = validity.not_after - validity.not_before {
-            match total_validity <= max_validity {
-                true => SimpleCheckResult::notice(format!(
-                    "Allowed validity: {} day(s)",
-                    total_validity.whole_days()
-                )),
-                false => SimpleCheckResult::warn(format!(
-                    "Allowed validity: {} day(s) but expected at most {}",
-                    total_validity.whole_days(),
-                    max_validity.whole_days(),
-                )),
-            }
+    let not_after = not_after.to_datetime();
+    let text = |deadline| {
+        format!(
+            "Certificate expires before {} deadline ({})",
+            format_deadline(deadline),
+            not_after.date()
+        )
+    };
+    match (crit, warn) {
+        (Some(crit), _) if not_after < crit => Some(SimpleCheckResult::crit(text(crit))),
+        (_, Some(warn)) if not_after < warn => Some(SimpleCheckResult::warn(text(warn))),
+        _ => None,
+    }
+}
+
+fn check_not_before(
+    not_before: ASN1Time,
+    now: ASN1Time,
+    skew: Duration,
+) -> Option<SimpleCheckResult> {
+    let time_to_start = not_before.to_datetime() - now.to_datetime();
+    (time_to_start > skew).then(|| {
+        // In hours, rounded up, under a day: "0 day(s)" would read as valid now.
+        let starts_in = if time_to_start < Duration::DAY {
+            let hours = (time_to_start.whole_seconds() + 3599) / 3600;
+            format!("{hours} hour(s)")
         } else {
-            SimpleCheckResult::crit("Invalid certificate validity")
-        }
+            format!("{} day(s)", time_to_start.whole_days())
+        };
+        SimpleCheckResult::crit(format!(
+            "Certificate not valid until {} (starts in {starts_in})",
+            not_before.to_datetime().date(),
+        ))
     })
 }
 
+fn check_age(
+    not_before: ASN1Time,
+    now: ASN1Time,
+    levels: Option<LevelsChecker<Duration>>,
+) -> CheckResult<Duration> {
+    // A certificate issued in the future is not old, `check_not_before` reports it.
+    let age = (now.to_datetime() - not_before.to_datetime()).max(Duration::ZERO);
+    check::check_levels(
+        age,
+        levels,
+        OutputType::Notice(format!("Certificate age: {} day(s)", age.whole_days())),
+        LevelsCheckerArgs::builder()
+            .label("cert_age")
+            .uom("s".parse().unwrap())
+            .build(),
+    )
+}
+
+fn check_max_validity(
+    validity: &Validity,
+    levels: Option<LevelsChecker<Duration>>,
+) -> CheckResult<Duration> {
+    let Some(lifetime) = validity.not_after - validity.not_before else {
+        return SimpleCheckResult::crit("Invalid certificate validity").into();
+    };
+    check::check_levels(
+        lifetime,
+        levels,
+        OutputType::Notice(format!(
+            "Certificate lifetime: {} day(s)",
+            lifetime.whole_days()
+        )),
+        LevelsCheckerArgs::builder()
+            .label("cert_lifetime")
+            .uom("s".parse().unwrap())
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod test_wildcard_labels {
+    use super::wildcard_labels;
+
+    #[test]
+    fn test_wildcard_labels() {
+        assert_eq!(wildcard_labels("*"), Some(0));
+        assert_eq!(wildcard_labels("*.com"), Some(1));
+        assert_eq!(wildcard_labels("*.example.com"), Some(2));
+        // A public suffix is not known as such.
+        assert_eq!(wildcard_labels("*.co.uk"), Some(2));
+        assert_eq!(wildcard_labels("*.example.co.uk."), Some(3));
+        assert_eq!(wildcard_labels("www.*.example.com"), None);
+    }
+}
+
 #[cfg(test)]
 mod test_check_serial {
     use super::{check_serial, SimpleCheckResult};
@@ -387,3 +1134,450 @@ mod test_check_serial {
         );
     }
 }
+
+#[cfg(test)]
+mod test_normalize_fingerprint {
+    use super::normalize_fingerprint;
+
+    #[test]
+    fn test_colons_and_case() {
+        assert_eq!(normalize_fingerprint("AA:bb:0C"), "aabb0c");
+        assert_eq!(normalize_fingerprint("aabb0c"), "aabb0c");
+        assert_eq!(normalize_fingerprint("AABB0C"), "aabb0c");
+    }
+}
+
+#[cfg(test)]
+mod test_normalize_dn {
+    use super::{name_attributes, normalize_dn};
+    use openssl::x509::X509NameBuilder;
+    use x509_parser::prelude::FromDer;
+    use x509_parser::x509::X509Name;
+
+    #[test]
+    fn test_order_and_whitespace() {
+        assert_eq!(
+            normalize_dn("C=US, O=Let's Encrypt, CN=R3"),
+            normalize_dn(" cn = r3 ,o=Let's   Encrypt,C=US ")
+        );
+    }
+
+    #[test]
+    fn test_openssl_format() {
+        assert_eq!(
+            normalize_dn("C=US, O=Let's Encrypt, CN=R3"),
+            normalize_dn("/C=US/O=Let's Encrypt/CN=R3")
+        );
+    }
+
+    #[test]
+    fn test_escaped_separator() {
+        assert_eq!(
+            normalize_dn(r"O=Foo\, Inc., C=US"),
+            vec![
+                (String::from("C"), String::from("us")),
+                (String::from("O"), String::from("foo, inc.")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comma_in_value() {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("C", "US").unwrap();
+        name.append_entry_by_text("O", "Entrust, Inc.").unwrap();
+        let der = name.build().to_der().unwrap();
+        let (_rem, name) = X509Name::from_der(&der).unwrap();
+        assert_eq!(
+            name_attributes(&name),
+            normalize_dn(r"C=US, O=Entrust\, Inc.")
+        );
+        assert_ne!(name_attributes(&name), normalize_dn("C=US, O=Entrust"));
+    }
+
+    #[test]
+    fn test_different() {
+        assert_ne!(normalize_dn("C=US, CN=R3"), normalize_dn("C=US, CN=E1"));
+        assert_ne!(normalize_dn("C=US, CN=R3"), normalize_dn("CN=R3"));
+    }
+}
+
+#[cfg(test)]
+mod test_matches_hostname {
+    use super::matches_hostname;
+
+    #[test]
+    fn test_exact() {
+        assert!(matches_hostname("example.com", "example.com"));
+        assert!(matches_hostname("Example.COM", "example.com."));
+        assert!(!matches_hostname("example.com", "www.example.com"));
+        assert!(!matches_hostname("localhost", "localhost2"));
+    }
+
+    #[test]
+    fn test_wildcard() {
+        assert!(matches_hostname("*.example.com", "www.example.com"));
+        assert!(!matches_hostname("*.example.com", "example.com"));
+        assert!(!matches_hostname("*.example.com", "a.b.example.com"));
+        assert!(matches_hostname("w*.example.com", "www.example.com"));
+        assert!(!matches_hostname("w*.example.com", "mail.example.com"));
+    }
+
+    #[test]
+    fn test_reject_multi_label_wildcard() {
+        assert!(!matches_hostname("*.*.com", "www.example.com"));
+        assert!(!matches_hostname("www.*.com", "www.example.com"));
+        assert!(!matches_hostname("**.example.com", "www.example.com"));
+        assert!(!matches_hostname("*", "localhost"));
+    }
+}
+
+#[cfg(test)]
+mod test_rsa_equivalent_bits {
+    use super::rsa_equivalent_bits;
+
+    #[test]
+    fn test_security_strengths() {
+        assert_eq!(rsa_equivalent_bits(80), 1024);
+        // P-224
+        assert_eq!(rsa_equivalent_bits(112), 2048);
+        // P-256 and Ed25519
+        assert_eq!(rsa_equivalent_bits(128), 3072);
+        // P-384
+        assert_eq!(rsa_equivalent_bits(192), 7680);
+        // Ed448, between two rows
+        assert_eq!(rsa_equivalent_bits(224), 7680);
+        // P-521
+        assert_eq!(rsa_equivalent_bits(256), 15360);
+    }
+}
+
+#[cfg(test)]
+mod test_check_validity {
+    use super::check_validity;
+    use crate::check::{self, Collection, Levels, LevelsChecker, LevelsStrategy, Real};
+    use crate::messages::Language;
+    use time::Duration;
+    use x509_parser::time::ASN1Time;
+
+    fn levels() -> LevelsChecker<Duration> {
+        LevelsChecker::try_new(
+            LevelsStrategy::Lower,
+            Levels {
+                warn: Duration::days(30),
+                crit: Duration::days(10),
+            },
+        )
+        .unwrap()
+    }
+
+    fn coll(not_after: ASN1Time, now: ASN1Time) -> Collection {
+        Collection::from(&mut vec![check_validity(
+            not_after,
+            now,
+            Some(levels()),
+            String::from("Subject: CN=example.com"),
+            Language::En,
+        )
+        .map(|x| Real::from(x.whole_seconds() as isize))])
+    }
+
+    fn ts(secs: i64) -> ASN1Time {
+        ASN1Time::from_timestamp(secs).unwrap()
+    }
+
+    #[test]
+    fn test_no_levels() {
+        let coll = Collection::from(&mut vec![check_validity(
+            ts(1_700_000_000 - 3 * 24 * 3600),
+            ts(1_700_000_000),
+            None,
+            String::new(),
+            Language::En,
+        )
+        .map(|x| Real::from(x.whole_seconds() as isize))]);
+        assert_eq!(
+            coll.to_string(),
+            "OK | certificate_remaining_validity=-259200s;;;;\n\
+            Certificate expired 3 day(s) ago (Nov 11 22:13:20 2023 +00:00)"
+        );
+    }
+
+    #[test]
+    fn test_ok() {
+        let now = ts(1_700_000_000);
+        let coll = coll(ts(1_700_000_000 + 60 * 24 * 3600), now);
+        assert_eq!(check::exit_code(&coll), 0);
+        assert!(coll
+            .to_string()
+            .contains("\nCertificate expires in 60 day(s)"));
+    }
+
+    #[test]
+    fn test_warn() {
+        let now = ts(1_700_000_000);
+        let coll = coll(ts(1_700_000_000 + 12 * 24 * 3600), now);
+        assert_eq!(check::exit_code(&coll), 1);
+        assert!(coll
+            .to_string()
+            .starts_with("Certificate expires in 12 day(s)"));
+        assert!(coll.to_string().ends_with("\nSubject: CN=example.com"));
+    }
+
+    #[test]
+    fn test_expired() {
+        let now = ts(1_700_000_000);
+        let coll = coll(ts(1_700_000_000 - 3 * 24 * 3600), now);
+        assert_eq!(check::exit_code(&coll), 2);
+        assert!(coll
+            .to_string()
+            .starts_with("Certificate expired 3 day(s) ago"));
+        assert!(coll
+            .to_string()
+            .contains("certificate_remaining_validity=-259200s;2592000;864000;;"));
+    }
+}
+
+#[cfg(test)]
+mod test_check_age {
+    use super::check_age;
+    use crate::check::{self, Collection, Levels, LevelsChecker, LevelsStrategy, Real};
+    use time::Duration;
+    use x509_parser::time::ASN1Time;
+
+    fn ts(secs: i64) -> ASN1Time {
+        ASN1Time::from_timestamp(secs).unwrap()
+    }
+
+    fn coll(not_before: ASN1Time, now: ASN1Time) -> Collection {
+        let levels = LevelsChecker::try_new(
+            LevelsStrategy::Upper,
+            Levels {
+                warn: Duration::days(90),
+                crit: Duration::days(365),
+            },
+        )
+        .unwrap();
+        Collection::from(&mut vec![check_age(not_before, now, Some(levels))
+            .map(|x| Real::from(x.whole_seconds() as isize))])
+    }
+
+    #[test]
+    fn test_no_levels() {
+        let coll = Collection::from(&mut vec![check_age(
+            ts(1_700_000_000 - 3 * 24 * 3600),
+            ts(1_700_000_000),
+            None,
+        )
+        .map(|x| Real::from(x.whole_seconds() as isize))]);
+        assert_eq!(
+            coll.to_string(),
+            "OK | cert_age=259200s;;;;\nCertificate age: 3 day(s)"
+        );
+    }
+
+    #[test]
+    fn test_warn() {
+        let coll = coll(ts(1_700_000_000 - 100 * 24 * 3600), ts(1_700_000_000));
+        assert_eq!(check::exit_code(&coll), 1);
+        assert_eq!(
+            coll.to_string(),
+            "Certificate age: 100 day(s) (above warn level) (!) \
+            | cert_age=8640000s;7776000;31536000;;"
+        );
+    }
+
+    #[test]
+    fn test_not_yet_valid() {
+        let coll = coll(ts(1_700_000_000 + 3600), ts(1_700_000_000));
+        assert_eq!(check::exit_code(&coll), 0);
+        assert!(coll.to_string().contains("cert_age=0s;7776000;31536000;;"));
+    }
+}
+
+#[cfg(test)]
+mod test_check_max_validity {
+    use super::check_max_validity;
+    use crate::check::{self, Collection, Levels, LevelsChecker, LevelsStrategy, Real};
+    use time::Duration;
+    use x509_parser::certificate::Validity;
+    use x509_parser::time::ASN1Time;
+
+    fn ts(secs: i64) -> ASN1Time {
+        ASN1Time::from_timestamp(secs).unwrap()
+    }
+
+    fn coll(not_before: ASN1Time, not_after: ASN1Time) -> Collection {
+        let levels = LevelsChecker::try_new(
+            LevelsStrategy::Upper,
+            Levels {
+                warn: Duration::days(398),
+                crit: Duration::days(825),
+            },
+        )
+        .unwrap();
+        Collection::from(&mut vec![check_max_validity(
+            &Validity {
+                not_before,
+                not_after,
+            },
+            Some(levels),
+        )
+        .map(|x| Real::from(x.whole_seconds() as isize))])
+    }
+
+    #[test]
+    fn test_no_levels() {
+        let coll = Collection::from(&mut vec![check_max_validity(
+            &Validity {
+                not_before: ts(1_700_000_000),
+                not_after: ts(1_700_000_000 + 90 * 24 * 3600),
+            },
+            None,
+        )
+        .map(|x| Real::from(x.whole_seconds() as isize))]);
+        assert_eq!(
+            coll.to_string(),
+            "OK | cert_lifetime=7776000s;;;;\nCertificate lifetime: 90 day(s)"
+        );
+    }
+
+    #[test]
+    fn test_levels() {
+        let ok = coll(ts(1_700_000_000), ts(1_700_000_000 + 397 * 24 * 3600));
+        assert_eq!(check::exit_code(&ok), 0);
+        let warn = coll(ts(1_700_000_000), ts(1_700_000_000 + 500 * 24 * 3600));
+        assert_eq!(check::exit_code(&warn), 1);
+        assert_eq!(
+            warn.to_string(),
+            "Certificate lifetime: 500 day(s) (above warn level) (!) \
+            | cert_lifetime=43200000s;34387200;71280000;;"
+        );
+        let crit = coll(ts(1_700_000_000), ts(1_700_000_000 + 3650 * 24 * 3600));
+        assert_eq!(check::exit_code(&crit), 2);
+    }
+
+    #[test]
+    fn test_inverted_validity() {
+        let coll = coll(ts(1_700_000_000), ts(1_700_000_000 - 3600));
+        assert_eq!(check::exit_code(&coll), 2);
+        assert!(coll.to_string().starts_with("Invalid certificate validity"));
+    }
+}
+
+#[cfg(test)]
+mod test_check_deadline {
+    use super::check_deadline;
+    use crate::check::SimpleCheckResult;
+    use time::macros::datetime;
+    use x509_parser::time::ASN1Time;
+
+    // 2023-11-14T22:13:20Z
+    fn not_after() -> ASN1Time {
+        ASN1Time::from_timestamp(1_700_000_000).unwrap()
+    }
+
+    #[test]
+    fn test_no_deadline() {
+        assert_eq!(check_deadline(not_after(), None, None), None);
+    }
+
+    #[test]
+    fn test_after_deadlines() {
+        assert_eq!(
+            check_deadline(
+                not_after(),
+                Some(datetime!(2023-11-01 0:00 UTC)),
+                Some(datetime!(2023-10-01 0:00 UTC))
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_warn() {
+        assert_eq!(
+            check_deadline(
+                not_after(),
+                Some(datetime!(2023-12-01 0:00 UTC)),
+                Some(datetime!(2023-11-01 0:00 UTC))
+            ),
+            Some(SimpleCheckResult::warn(
+                "Certificate expires before 2023-12-01 deadline (2023-11-14)"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_crit() {
+        assert_eq!(
+            check_deadline(
+                not_after(),
+                Some(datetime!(2024-01-01 0:00 UTC)),
+                Some(datetime!(2023-11-15 12:00 +2))
+            ),
+            Some(SimpleCheckResult::crit(
+                "Certificate expires before 2023-11-15T10:00:00Z deadline (2023-11-14)"
+            ))
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_check_not_before {
+    use super::check_not_before;
+    use crate::check::SimpleCheckResult;
+    use time::Duration;
+    use x509_parser::time::ASN1Time;
+
+    fn ts(secs: i64) -> ASN1Time {
+        ASN1Time::from_timestamp(secs).unwrap()
+    }
+
+    #[test]
+    fn test_valid() {
+        let now = ts(1_700_000_000);
+        assert_eq!(
+            check_not_before(ts(1_700_000_000 - 3600), now, Duration::minutes(5)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_within_skew() {
+        let now = ts(1_700_000_000);
+        assert_eq!(
+            check_not_before(ts(1_700_000_000 + 60), now, Duration::minutes(5)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_not_yet_valid() {
+        let now = ts(1_700_000_000);
+        assert_eq!(
+            check_not_before(ts(1_700_000_000 + 3 * 24 * 3600), now, Duration::minutes(5)),
+            Some(SimpleCheckResult::crit(
+                "Certificate not valid until 2023-11-17 (starts in 3 day(s))"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_not_yet_valid_within_a_day() {
+        let now = ts(1_700_000_000);
+        for (secs, text) in [
+            (600, "starts in 1 hour(s)"),
+            (23 * 3600 + 1800, "starts in 24 hour(s)"),
+            (24 * 3600, "starts in 1 day(s)"),
+        ] {
+            let start = ts(1_700_000_000 + secs).to_datetime().date();
+            assert_eq!(
+                check_not_before(ts(1_700_000_000 + secs), now, Duration::minutes(5)),
+                Some(SimpleCheckResult::crit(format!(
+                    "Certificate not valid until {start} ({text})"
+                )))
+            );
+        }
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+use crate::check::{
+    self, CheckResult, Collection, LevelsChecker, LevelsCheckerArgs, Metric, OutputType, Real,
+    SimpleCheckResult,
+};
+use time::Duration;
+use typed_builder::TypedBuilder;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+
+#[derive(Debug, TypedBuilder)]
+#[builder(field_defaults(default))]
+pub struct Config {
+    not_after: Option<LevelsChecker<Duration>>,
+}
+
+pub fn check(chain: &[Vec<u8>], config: Config) -> Collection {
+    match config.not_after {
+        None => Collection::default(),
+        Some(levels) => Collection::from(&mut vec![check_chain_expiry(
+            chain,
+            ASN1Time::now(),
+            Some(levels),
+        )
+        .map(|x| Real::from(x.whole_seconds() as isize))
+        .tag("chain_expiry")]),
+    }
+}
+
+fn describe(depth: usize) -> String {
+    match depth {
+        0 => String::from("leaf"),
+        depth => format!("intermediate at depth {depth}"),
+    }
+}
+
+fn check_chain_expiry(
+    chain: &[Vec<u8>],
+    now: ASN1Time,
+    levels: Option<LevelsChecker<Duration>>,
+) -> CheckResult<Duration> {
+    let mut soonest: Option<(usize, ASN1Time)> = None;
+    for (depth, der) in chain.iter().enumerate() {
+        let Ok((_rem, cert)) = X509Certificate::from_der(der) else {
+            return SimpleCheckResult::unknown(format!(
+                "Chain: failed to parse certificate at depth {depth}"
+            ))
+            .into();
+        };
+        let not_after = cert.validity().not_after;
+        if soonest.map_or(true, |(_, soonest)| not_after < soonest) {
+            soonest = Some((depth, not_after));
+        }
+    }
+    let Some((depth, not_after)) = soonest else {
+        return SimpleCheckResult::unknown("Chain: no certificates").into();
+    };
+
+    // `ASN1Time` subtraction is unsigned, go through the (signed) datetimes.
+    let remaining = not_after.to_datetime() - now.to_datetime();
+    if remaining.is_negative() {
+        // An expired member breaks the chain whatever the levels say.
+        return CheckResult::crit(
+            format!(
+                "Chain: {} expired {} day(s) ago ({not_after})",
+                describe(depth),
+                remaining.abs().whole_days(),
+            ),
+            Metric::builder()
+                .label("chain_remaining_validity")
+                .value(remaining)
+                .uom("s".parse().unwrap())
+                .build(),
+        );
+    }
+    check::check_levels(
+        remaining,
+        levels,
+        OutputType::Notice(format!(
+            "Chain of {} certificate(s), {} expires soonest in {} day(s) ({not_after})",
+            chain.len(),
+            describe(depth),
+            remaining.whole_days(),
+        )),
+        LevelsCheckerArgs::builder()
+            .label("chain_remaining_validity")
+            .uom("s".parse().unwrap())
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod test_check_chain_expiry {
+    use super::check_chain_expiry;
+    use crate::check::{self, Collection, Levels, LevelsChecker, LevelsStrategy, Real};
+    use time::Duration;
+    use x509_parser::time::ASN1Time;
+
+    static LEAF: &[u8] = include_bytes!("../../assets/ocsp-leaf.der");
+    static CA: &[u8] = include_bytes!("../../assets/ocsp-ca.der");
+    static EXPIRED: &[u8] = include_bytes!("../../assets/certificate.der");
+
+    fn coll(chain: &[&[u8]], now: ASN1Time) -> Collection {
+        let levels = LevelsChecker::try_new(
+            LevelsStrategy::Lower,
+            Levels {
+                warn: Duration::days(30),
+                crit: Duration::days(0),
+            },
+        )
+        .unwrap();
+        let chain = chain.iter().map(|der| der.to_vec()).collect::<Vec<_>>();
+        Collection::from(&mut vec![check_chain_expiry(&chain, now, Some(levels))
+            .map(|x| Real::from(x.whole_seconds() as isize))])
+    }
+
+    #[test]
+    fn test_soonest() {
+        let coll = coll(
+            &[LEAF, CA],
+            ASN1Time::from_timestamp(1_700_000_000).unwrap(),
+        );
+        assert_eq!(check::exit_code(&coll), 0);
+        assert!(coll.to_string().contains(
+            "\nChain of 2 certificate(s), intermediate at depth 1 expires soonest in 37564 day(s)"
+        ));
+    }
+
+    #[test]
+    fn test_expired_intermediate() {
+        let coll = coll(
+            &[LEAF, EXPIRED, CA],
+            ASN1Time::from_timestamp(1_700_000_000).unwrap(),
+        );
+        assert_eq!(check::exit_code(&coll), 2);
+        assert!(coll
+            .to_string()
+            .starts_with("Chain: intermediate at depth 1 expired 1496 day(s) ago"));
+    }
+
+    #[test]
+    fn test_empty() {
+        let coll = coll(&[], ASN1Time::from_timestamp(1_700_000_000).unwrap());
+        assert_eq!(check::exit_code(&coll), 3);
+    }
+}
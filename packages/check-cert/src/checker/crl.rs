@@ -0,0 +1,88 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+use crate::check::{Collection, SimpleCheckResult};
+use anyhow::Result;
+use openssl::asn1::Asn1Time;
+use openssl::x509::{CrlStatus, X509Crl, X509};
+use std::cmp::Ordering;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{DistributionPointName, GeneralName, ParsedExtension};
+use x509_parser::prelude::FromDer;
+
+/// HTTP URIs of the CRL distribution points of the certificate.
+pub fn distribution_points(der: &[u8]) -> Vec<String> {
+    let Ok((_rem, cert)) = X509Certificate::from_der(der) else {
+        return Vec::new();
+    };
+    cert.extensions()
+        .iter()
+        .filter_map(|ext| match ext.parsed_extension() {
+            ParsedExtension::CRLDistributionPoints(points) => Some(points),
+            _ => None,
+        })
+        .flat_map(|points| points.iter())
+        .filter_map(|point| match &point.distribution_point {
+            Some(DistributionPointName::FullName(names)) => Some(names),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|name| match name {
+            GeneralName::URI(uri) if uri.starts_with("http://") => Some(uri.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn check(crl: Result<Vec<u8>>, chain: &[Vec<u8>]) -> Collection {
+    assert!(!chain.is_empty());
+
+    Collection::from(check_crl(crl, chain).tag("crl"))
+}
+
+fn parse(crl: &[u8]) -> Option<X509Crl> {
+    if crl.starts_with(b"-----BEGIN") {
+        X509Crl::from_pem(crl).ok()
+    } else {
+        X509Crl::from_der(crl).ok()
+    }
+}
+
+fn check_crl(crl: Result<Vec<u8>>, chain: &[Vec<u8>]) -> SimpleCheckResult {
+    let crl = match crl {
+        Ok(crl) => crl,
+        Err(err) => return SimpleCheckResult::warn(format!("CRL: {err}")),
+    };
+    let Some(crl) = parse(&crl) else {
+        return SimpleCheckResult::warn("CRL: invalid revocation list");
+    };
+    let (Some(Ok(cert)), Some(Ok(issuer))) = (
+        chain.first().map(|der| X509::from_der(der)),
+        chain.get(1).map(|der| X509::from_der(der)),
+    ) else {
+        return SimpleCheckResult::unknown("CRL: cannot verify revocation list, issuer missing");
+    };
+    if !issuer
+        .public_key()
+        .and_then(|key| crl.verify(&key))
+        .unwrap_or(false)
+    {
+        return SimpleCheckResult::warn("CRL: revocation list not signed by the issuer");
+    }
+    let next_update = crl
+        .next_update()
+        .map_or(String::from("none"), ToString::to_string);
+    if let CrlStatus::Revoked(entry) = crl.get_by_serial(cert.serial_number()) {
+        return SimpleCheckResult::crit(format!("CRL: revoked on {}", entry.revocation_date()));
+    }
+    let expired = match (crl.next_update(), Asn1Time::days_from_now(0)) {
+        (Some(next_update), Ok(now)) => next_update.compare(&now).ok() == Some(Ordering::Less),
+        _ => false,
+    };
+    if expired {
+        SimpleCheckResult::warn(format!("CRL: outdated (next update: {next_update})"))
+    } else {
+        SimpleCheckResult::ok(format!("CRL: not revoked (next update: {next_update})"))
+    }
+}
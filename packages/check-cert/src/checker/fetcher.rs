@@ -2,7 +2,12 @@
 // This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
 // conditions defined in the file COPYING, which is part of this source code package.
 
-use crate::check::{CheckResult, Collection, LevelsChecker, LevelsCheckerArgs, OutputType, Real};
+use crate::check::{
+    self, CheckResult, Collection, LevelsChecker, LevelsCheckerArgs, Metric, OutputType, Real,
+    SimpleCheckResult,
+};
+use crate::fetcher::{Attempts, Timings};
+use crate::messages::{self, Language, Phase, TimeUnit};
 use std::time::Duration;
 use typed_builder::TypedBuilder;
 
@@ -10,29 +15,227 @@ use typed_builder::TypedBuilder;
 #[builder(field_defaults(default))]
 pub struct Config {
     response_time: Option<LevelsChecker<Duration>>,
+    language: Language,
+    /// Unit of both the text and the metrics, defaults to ms in the text and s in the metrics.
+    time_unit: Option<TimeUnit>,
 }
 
-pub fn check(response_time: Duration, config: Config) -> Collection {
-    Collection::from(&mut vec![check_response_time(
-        response_time,
+fn uom(unit: TimeUnit) -> &'static str {
+    match unit {
+        TimeUnit::Seconds => "s",
+        TimeUnit::Milliseconds => "ms",
+    }
+}
+
+fn in_unit(duration: Duration, unit: TimeUnit) -> Real {
+    match unit {
+        TimeUnit::Seconds => Real::from(duration.as_secs_f64()),
+        TimeUnit::Milliseconds => Real::from(duration.as_secs_f64() * 1000.0),
+    }
+}
+
+pub fn check(timings: Timings, attempts: Attempts, config: Config) -> Collection {
+    let text_unit = config.time_unit.unwrap_or(TimeUnit::Milliseconds);
+    let metric_unit = config.time_unit.unwrap_or(TimeUnit::Seconds);
+    let response_time = check_response_time(
+        timings,
         config.response_time,
+        config.language,
+        text_unit,
+        metric_unit,
     )
-    .unwrap_or_default()
-    .map(|x| Real::from(x.as_secs_f64()))])
+    .map(|x| in_unit(x, metric_unit))
+    .tag("response_time");
+    let mut check_results = vec![response_time];
+    check_results
+        .extend(check_attempts(attempts, config.language).map(|r| r.tag("attempts").into()));
+    check_results.extend(
+        timings
+            .dns_resolve
+            .map(|t| phase_time("dns_resolve_time", t, metric_unit)),
+    );
+    check_results.extend(
+        timings
+            .tcp_connect
+            .map(|t| phase_time("tcp_connect_time", t, metric_unit)),
+    );
+    check_results.extend(
+        timings
+            .proxy_connect
+            .map(|t| phase_time("proxy_connect_time", t, metric_unit)),
+    );
+    check_results.extend(
+        timings
+            .starttls
+            .map(|t| phase_time("starttls_time", t, metric_unit)),
+    );
+    check_results.push(phase_time(
+        "tls_handshake_time",
+        timings.tls_handshake,
+        metric_unit,
+    ));
+    Collection::from(&mut check_results)
 }
 
 fn check_response_time(
-    response_time: Duration,
+    timings: Timings,
     levels: Option<LevelsChecker<Duration>>,
-) -> Option<CheckResult<Duration>> {
-    levels.map(|levels| {
-        levels.check(
-            response_time,
-            OutputType::Notice(format!("Response time: {} ms", response_time.as_millis())),
-            LevelsCheckerArgs::builder()
-                .label("overall_response_time")
-                .uom("s".parse().unwrap())
-                .build(),
-        )
+    lang: Language,
+    text_unit: TimeUnit,
+    metric_unit: TimeUnit,
+) -> CheckResult<Duration> {
+    let phases = [
+        (Phase::DnsResolve, timings.dns_resolve),
+        (Phase::TcpConnect, timings.tcp_connect),
+        (Phase::ProxyConnect, timings.proxy_connect),
+        (Phase::StartTls, timings.starttls),
+        (Phase::TlsHandshake, Some(timings.tls_handshake)),
+    ];
+    check::check_levels(
+        timings.overall,
+        levels,
+        OutputType::Details(
+            messages::response_time(lang, timings.overall, text_unit),
+            phases
+                .iter()
+                .filter_map(|(phase, t)| {
+                    t.map(|t| messages::phase_time(lang, *phase, t, text_unit))
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        LevelsCheckerArgs::builder()
+            .label("overall_response_time")
+            .uom(uom(metric_unit).parse().unwrap())
+            .build(),
+    )
+}
+
+fn check_attempts(attempts: Attempts, lang: Language) -> Option<SimpleCheckResult> {
+    // Flaky but working: say so without raising the state.
+    (attempts.made > 1).then(|| {
+        SimpleCheckResult::ok(messages::succeeded_on_attempt(
+            lang,
+            attempts.made,
+            attempts.allowed,
+        ))
     })
 }
+
+fn phase_time(label: &str, duration: Duration, unit: TimeUnit) -> CheckResult<Real> {
+    // Informational only, the levels apply to the overall response time.
+    CheckResult::notice(
+        "",
+        Metric::builder()
+            .label(label)
+            .value(in_unit(duration, unit))
+            .uom(uom(unit).parse().unwrap())
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod test_check {
+    use super::{check, Config};
+    use crate::fetcher::{Attempts, Timings};
+    use crate::messages::TimeUnit;
+    use std::time::Duration;
+
+    const FIRST: Attempts = Attempts {
+        made: 1,
+        allowed: 3,
+    };
+
+    fn timings() -> Timings {
+        Timings {
+            overall: Duration::from_millis(300),
+            dns_resolve: Some(Duration::from_millis(20)),
+            tcp_connect: Some(Duration::from_millis(100)),
+            proxy_connect: None,
+            starttls: Some(Duration::from_millis(50)),
+            tls_handshake: Duration::from_millis(150),
+        }
+    }
+
+    #[test]
+    fn test_phase_metrics() {
+        assert_eq!(
+            check(timings(), FIRST, Config::builder().build()).to_string(),
+            "OK | overall_response_time=0.300000s;;;; \
+            dns_resolve_time=0.020000s;;;; \
+            tcp_connect_time=0.100000s;;;; \
+            starttls_time=0.050000s;;;; \
+            tls_handshake_time=0.150000s;;;;\n\
+            Response time: 300 ms\n\
+            DNS resolution: 20 ms\n\
+            TCP connect: 100 ms\n\
+            STARTTLS: 50 ms\n\
+            TLS handshake: 150 ms"
+        );
+    }
+
+    #[test]
+    fn test_unix_socket() {
+        let timings = Timings {
+            dns_resolve: None,
+            tcp_connect: None,
+            starttls: None,
+            ..timings()
+        };
+        assert_eq!(
+            check(timings, FIRST, Config::builder().build()).to_string(),
+            "OK | overall_response_time=0.300000s;;;; \
+            tls_handshake_time=0.150000s;;;;\n\
+            Response time: 300 ms\n\
+            TLS handshake: 150 ms"
+        );
+    }
+
+    #[test]
+    fn test_retried() {
+        let attempts = Attempts {
+            made: 2,
+            allowed: 3,
+        };
+        let coll = check(timings(), attempts, Config::builder().build());
+        assert!(coll
+            .to_string()
+            .starts_with("Connection succeeded on attempt 2/3 | "));
+        assert_eq!(crate::check::exit_code(&coll), 0);
+    }
+
+    #[test]
+    fn test_time_unit() {
+        for (unit, expected) in [
+            (
+                TimeUnit::Milliseconds,
+                "OK | overall_response_time=300.000000ms;;;; \
+                dns_resolve_time=20.000000ms;;;; \
+                tcp_connect_time=100.000000ms;;;; \
+                starttls_time=50.000000ms;;;; \
+                tls_handshake_time=150.000000ms;;;;\n\
+                Response time: 300 ms\n\
+                DNS resolution: 20 ms\n\
+                TCP connect: 100 ms\n\
+                STARTTLS: 50 ms\n\
+                TLS handshake: 150 ms",
+            ),
+            (
+                TimeUnit::Seconds,
+                "OK | overall_response_time=0.300000s;;;; \
+                dns_resolve_time=0.020000s;;;; \
+                tcp_connect_time=0.100000s;;;; \
+                starttls_time=0.050000s;;;; \
+                tls_handshake_time=0.150000s;;;;\n\
+                Response time: 0.300 s\n\
+                DNS resolution: 0.020 s\n\
+                TCP connect: 0.100 s\n\
+                STARTTLS: 0.050 s\n\
+                TLS handshake: 0.150 s",
+            ),
+        ] {
+            let config = Config::builder().time_unit(Some(unit)).build();
+            assert_eq!(check(timings(), FIRST, config).to_string(), expected);
+        }
+    }
+}
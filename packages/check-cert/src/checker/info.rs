@@ -3,17 +3,109 @@
 // conditions defined in the file COPYING, which is part of this source code package.
 
 use crate::check::{Collection, SimpleCheckResult};
+use std::net::IpAddr;
+use std::path::Path;
 use typed_builder::TypedBuilder;
 
 #[derive(Debug, TypedBuilder)]
 pub struct Config<'a> {
     server: &'a str,
     port: u16,
+    /// Address connected to, none over a UNIX socket.
+    #[builder(default)]
+    addr: Option<IpAddr>,
+    /// Name sent with SNI if not the server.
+    #[builder(default)]
+    server_name: Option<&'a str>,
+    /// Name the certificate is validated against if not the server.
+    #[builder(default)]
+    validation_host: Option<&'a str>,
+    /// Socket connected to in place of the port.
+    #[builder(default)]
+    unix_socket: Option<&'a Path>,
 }
 
 pub fn collect(config: Config) -> Collection {
-    Collection::from(&mut vec![
-        SimpleCheckResult::notice(format!("Host to test: {}", config.server)).into(),
-        SimpleCheckResult::notice(format!("Host port: {}", config.port)).into(),
-    ])
+    let mut check_results = vec![
+        SimpleCheckResult::notice(format!("Host to test: {}", config.server))
+            .tag("info")
+            .into(),
+        match config.unix_socket {
+            Some(path) => SimpleCheckResult::notice(format!("Host socket: {}", path.display())),
+            None => SimpleCheckResult::notice(format!("Host port: {}", config.port)),
+        }
+        .tag("info")
+        .into(),
+    ];
+    check_results.extend(config.addr.map(|addr| {
+        SimpleCheckResult::ok(format!("Connected to {addr}"))
+            .tag("info")
+            .into()
+    }));
+    // Make it clear which names were used when they differ from the server.
+    let server = config.server;
+    check_results.extend(
+        config
+            .server_name
+            .filter(|name| *name != server)
+            .map(|name| {
+                SimpleCheckResult::ok(format!("SNI: {name}"))
+                    .tag("info")
+                    .into()
+            }),
+    );
+    check_results.extend(
+        config
+            .validation_host
+            .filter(|name| *name != server)
+            .map(|name| {
+                SimpleCheckResult::ok(format!("Validated name: {name}"))
+                    .tag("info")
+                    .into()
+            }),
+    );
+    Collection::from(&mut check_results)
+}
+
+#[cfg(test)]
+mod test_collect {
+    use super::{collect, Config};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn test_names() {
+        let coll = collect(
+            Config::builder()
+                .server("backend.example.com")
+                .port(443)
+                .addr(Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))))
+                .server_name(Some("www.example.com"))
+                .validation_host(Some("backend.example.com"))
+                .build(),
+        );
+        assert_eq!(
+            coll.to_string(),
+            "Connected to 192.0.2.1, SNI: www.example.com\n\
+            Host to test: backend.example.com\n\
+            Host port: 443"
+        );
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut coll = collect(
+            Config::builder()
+                .server("backend.example.com")
+                .port(443)
+                .server_name(Some("www.example.com"))
+                .build(),
+        );
+        coll.show_tags();
+        assert_eq!(
+            coll.to_string(),
+            "[info] SNI: www.example.com\n\
+            [info] Host to test: backend.example.com\n\
+            [info] Host port: 443"
+        );
+    }
 }
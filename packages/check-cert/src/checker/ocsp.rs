@@ -0,0 +1,103 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+use crate::check::{Collection, SimpleCheckResult};
+use openssl::hash::MessageDigest;
+use openssl::ocsp::{OcspCertId, OcspCertStatus, OcspFlag, OcspResponse, OcspResponseStatus};
+use openssl::stack::Stack;
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::verify::X509VerifyFlags;
+use openssl::x509::{X509Ref, X509};
+use typed_builder::TypedBuilder;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub enum MissingStaple {
+    #[default]
+    Ok,
+    Warn,
+    Crit,
+}
+
+#[derive(Debug, TypedBuilder)]
+#[builder(field_defaults(default))]
+pub struct Config {
+    missing_staple: MissingStaple,
+}
+
+pub fn check(staple: Option<&[u8]>, chain: &[Vec<u8>], config: Config) -> Collection {
+    assert!(!chain.is_empty());
+
+    Collection::from(check_ocsp_staple(staple, chain, config.missing_staple).tag("ocsp"))
+}
+
+/// The response is signed by the issuer or by a responder the issuer delegated to.
+fn signed_by_issuer(basic: &openssl::ocsp::OcspBasicResponseRef, issuer: &X509Ref) -> bool {
+    // The issuer is trusted as is, it need not be a root.
+    let store = X509StoreBuilder::new().and_then(|mut store| {
+        store.add_cert(issuer.to_owned())?;
+        store.set_flags(X509VerifyFlags::PARTIAL_CHAIN)?;
+        Ok(store.build())
+    });
+    let certs = Stack::new().and_then(|mut certs| {
+        certs.push(issuer.to_owned())?;
+        Ok(certs)
+    });
+    match (store, certs) {
+        (Ok(store), Ok(certs)) => basic.verify(&certs, &store, OcspFlag::empty()).is_ok(),
+        _ => false,
+    }
+}
+
+fn check_ocsp_staple(
+    staple: Option<&[u8]>,
+    chain: &[Vec<u8>],
+    missing_staple: MissingStaple,
+) -> SimpleCheckResult {
+    let Some(staple) = staple else {
+        let text = "No OCSP response stapled";
+        return match missing_staple {
+            MissingStaple::Ok => SimpleCheckResult::notice(text),
+            MissingStaple::Warn => SimpleCheckResult::warn(text),
+            MissingStaple::Crit => SimpleCheckResult::crit(text),
+        };
+    };
+    let Ok(response) = OcspResponse::from_der(staple) else {
+        return SimpleCheckResult::warn("OCSP: invalid stapled response");
+    };
+    if response.status() != OcspResponseStatus::SUCCESSFUL {
+        return SimpleCheckResult::warn(format!(
+            "OCSP: unsuccessful response (status {})",
+            response.status().as_raw()
+        ));
+    }
+    let (Ok(basic), Some(Ok(cert)), Some(Ok(issuer))) = (
+        response.basic(),
+        chain.first().map(|der| X509::from_der(der)),
+        chain.get(1).map(|der| X509::from_der(der)),
+    ) else {
+        return SimpleCheckResult::unknown("OCSP: cannot identify certificate, issuer missing");
+    };
+    let Ok(cert_id) = OcspCertId::from_cert(MessageDigest::sha1(), &cert, &issuer) else {
+        return SimpleCheckResult::unknown("OCSP: cannot identify certificate");
+    };
+    if !signed_by_issuer(&basic, &issuer) {
+        return SimpleCheckResult::crit("OCSP: stapled response not signed by the issuer");
+    }
+    let Some(status) = basic.find_status(&cert_id) else {
+        return SimpleCheckResult::warn("OCSP: response does not cover the certificate");
+    };
+    let updates = format!(
+        "this update: {}, next update: {}",
+        status.this_update, status.next_update
+    );
+    if status.status == OcspCertStatus::REVOKED {
+        SimpleCheckResult::crit(format!("OCSP: revoked ({updates})"))
+    } else if status.status != OcspCertStatus::GOOD {
+        SimpleCheckResult::warn(format!("OCSP: unknown ({updates})"))
+    } else if status.check_validity(300, None).is_err() {
+        SimpleCheckResult::warn(format!("OCSP: good but outdated ({updates})"))
+    } else {
+        SimpleCheckResult::ok(format!("OCSP: good ({updates})"))
+    }
+}
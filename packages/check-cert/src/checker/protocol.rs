@@ -0,0 +1,191 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+use crate::check::{CheckResult, Collection, Metric, Real, SimpleCheckResult};
+use crate::fetcher::{Cipher, TlsVersion};
+use typed_builder::TypedBuilder;
+
+/// Components of the cipher names that make the cipher weak.
+const WEAK_CIPHERS: &[&str] = &["NULL", "EXP", "EXPORT", "RC4", "DES", "CBC3", "3DES"];
+
+/// Key exchanges without forward secrecy.
+const STATIC_KX: &[&str] = &[
+    "RSA",
+    "PSK",
+    "RSAPSK",
+    "ECDH/RSA",
+    "ECDH/ECDSA",
+    "DH/RSA",
+    "DH/DSS",
+];
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(ToString::to_string).collect()
+}
+
+#[derive(Debug, TypedBuilder)]
+#[builder(field_defaults(default))]
+pub struct Config {
+    min_version: Option<TlsVersion>,
+    /// Components of the cipher name, between `-` or `_`, that are CRIT.
+    #[builder(default = to_strings(WEAK_CIPHERS))]
+    weak_ciphers: Vec<String>,
+    /// Key exchanges, as in `openssl ciphers -v`, that are WARN.
+    #[builder(default = to_strings(STATIC_KX))]
+    static_kx: Vec<String>,
+}
+
+impl Config {
+    pub fn min_version(&self) -> Option<TlsVersion> {
+        self.min_version
+    }
+}
+
+pub fn check(version: Option<TlsVersion>, cipher: Option<&Cipher>, config: Config) -> Collection {
+    Collection::from(&mut vec![
+        check_protocol_version(version, config.min_version)
+            .tag("protocol")
+            .into(),
+        check_cipher(cipher, &config.weak_ciphers, &config.static_kx)
+            .map(|x| Real::from(x as isize))
+            .tag("cipher"),
+    ])
+}
+
+fn check_protocol_version(
+    version: Option<TlsVersion>,
+    min_version: Option<TlsVersion>,
+) -> SimpleCheckResult {
+    match (version, min_version) {
+        (None, None) => SimpleCheckResult::notice("Negotiated protocol unknown"),
+        (None, Some(_)) => SimpleCheckResult::unknown("Negotiated protocol unknown"),
+        (Some(version), Some(min_version)) if version < min_version => {
+            SimpleCheckResult::crit(format!("Negotiated {version}, below minimum {min_version}"))
+        }
+        (Some(version), _) => SimpleCheckResult::ok(format!("Negotiated {version}")),
+    }
+}
+
+fn check_cipher(
+    cipher: Option<&Cipher>,
+    weak_ciphers: &[String],
+    static_kx: &[String],
+) -> CheckResult<u32> {
+    let Some(cipher) = cipher else {
+        return SimpleCheckResult::notice("Negotiated cipher unknown").into();
+    };
+    let name = &cipher.name;
+    let metric = Metric::builder()
+        .label("cipher_bits")
+        .value(cipher.bits)
+        .build();
+    if name.split(['-', '_']).any(|part| {
+        weak_ciphers
+            .iter()
+            .any(|weak| weak.eq_ignore_ascii_case(part))
+    }) {
+        CheckResult::crit(format!("Cipher: {name} is weak"), metric)
+    } else if cipher
+        .kx
+        .as_ref()
+        .is_some_and(|kx| static_kx.iter().any(|s| s.eq_ignore_ascii_case(kx)))
+    {
+        CheckResult::warn(format!("Cipher: {name} without forward secrecy"), metric)
+    } else {
+        CheckResult::ok(format!("Cipher: {name}"), metric)
+    }
+}
+
+#[cfg(test)]
+mod test_check_cipher {
+    use super::{check_cipher, to_strings, STATIC_KX, WEAK_CIPHERS};
+    use crate::check::{Collection, Real};
+    use crate::fetcher::Cipher;
+
+    fn check(name: &str, kx: Option<&str>) -> String {
+        let cipher = Cipher {
+            name: String::from(name),
+            kx: kx.map(String::from),
+            bits: 128,
+        };
+        Collection::from(&mut vec![check_cipher(
+            Some(&cipher),
+            &to_strings(WEAK_CIPHERS),
+            &to_strings(STATIC_KX),
+        )
+        .map(|x| Real::from(x as isize))])
+        .to_string()
+    }
+
+    #[test]
+    fn test_forward_secret() {
+        assert_eq!(
+            check("ECDHE-RSA-AES128-GCM-SHA256", Some("ECDH")),
+            "Cipher: ECDHE-RSA-AES128-GCM-SHA256 | cipher_bits=128;;;;"
+        );
+        assert_eq!(
+            check("TLS_AES_128_GCM_SHA256", Some("any")),
+            "Cipher: TLS_AES_128_GCM_SHA256 | cipher_bits=128;;;;"
+        );
+    }
+
+    #[test]
+    fn test_weak() {
+        assert_eq!(
+            check("DES-CBC3-SHA", Some("RSA")),
+            "Cipher: DES-CBC3-SHA is weak (!!) | cipher_bits=128;;;;"
+        );
+        assert_eq!(
+            check("ECDHE-RSA-RC4-SHA", Some("ECDH")),
+            "Cipher: ECDHE-RSA-RC4-SHA is weak (!!) | cipher_bits=128;;;;"
+        );
+    }
+
+    #[test]
+    fn test_static_kx() {
+        assert_eq!(
+            check("AES128-GCM-SHA256", Some("RSA")),
+            "Cipher: AES128-GCM-SHA256 without forward secrecy (!) | cipher_bits=128;;;;"
+        );
+    }
+
+    #[test]
+    fn test_unknown() {
+        assert_eq!(
+            check("FANCY-NEW-CIPHER", None),
+            "Cipher: FANCY-NEW-CIPHER | cipher_bits=128;;;;"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_check_protocol_version {
+    use super::check_protocol_version;
+    use crate::check::SimpleCheckResult;
+    use crate::fetcher::TlsVersion;
+
+    #[test]
+    fn test_no_minimum() {
+        assert_eq!(
+            check_protocol_version(Some(TlsVersion::Tls1_0), None),
+            SimpleCheckResult::ok("Negotiated TLS 1.0")
+        );
+    }
+
+    #[test]
+    fn test_minimum() {
+        assert_eq!(
+            check_protocol_version(Some(TlsVersion::Tls1_2), Some(TlsVersion::Tls1_2)),
+            SimpleCheckResult::ok("Negotiated TLS 1.2")
+        );
+        assert_eq!(
+            check_protocol_version(Some(TlsVersion::Tls1_3), Some(TlsVersion::Tls1_2)),
+            SimpleCheckResult::ok("Negotiated TLS 1.3")
+        );
+        assert_eq!(
+            check_protocol_version(Some(TlsVersion::Tls1_0), Some(TlsVersion::Tls1_2)),
+            SimpleCheckResult::crit("Negotiated TLS 1.0, below minimum TLS 1.2")
+        );
+    }
+}
@@ -32,14 +32,17 @@ mod verify {
         (cert, stack)
     }
 
-    pub fn verify(chain: &[Vec<u8>], cacerts: &[Vec<u8>]) -> (bool, X509VerifyResult) {
+    pub fn verify(chain: &[Vec<u8>], cacerts: &[Vec<u8>]) -> (bool, X509VerifyResult, usize) {
         let store = make_store(cacerts);
         let (cert, chain) = make_stack(chain);
 
         let mut verify_ctx = X509StoreContext::new().unwrap();
-        let result = verify_ctx.init(&store, &cert, &chain, X509StoreContextRef::verify_cert);
+        let result = verify_ctx.init(&store, &cert, &chain, |ctx| {
+            let ok = X509StoreContextRef::verify_cert(ctx)?;
+            Ok((ok, ctx.error(), ctx.chain().map_or(0, |c| c.len())))
+        });
 
-        (result.unwrap(), verify_ctx.error())
+        result.unwrap()
     }
 }
 
@@ -54,30 +57,29 @@ pub struct Config<'a> {
 pub fn check(chain: &[Vec<u8>], config: Config) -> Collection {
     assert!(!chain.is_empty());
 
-    Collection::from(check_verify_chain(
-        chain,
-        config.trust_store,
-        config.allow_self_signed,
-    ))
+    Collection::from(
+        check_chain(chain, config.trust_store, config.allow_self_signed).tag("verification"),
+    )
 }
 
-fn check_verify_chain(
+fn check_chain(
     chain: &[Vec<u8>],
     cacerts: &[Vec<u8>],
     allow_self_signed: bool,
 ) -> SimpleCheckResult {
-    let (ok, reason) = verify::verify(chain, cacerts);
+    let (ok, reason, depth) = verify::verify(chain, cacerts);
     if ok {
-        SimpleCheckResult::ok_with_details("Verification: OK", "Verification: OK")
+        let text = format!("Chain verified ({depth} certificate(s))");
+        SimpleCheckResult::ok_with_details(&text, &text)
     } else if reason.as_raw() == 18 && allow_self_signed {
         SimpleCheckResult::ok_with_details(
             format!("Verification: {reason} (allowed)"),
             format!("Verification: {reason} (allowed)"),
         )
     } else {
-        SimpleCheckResult::warn_with_details(
-            format!("Verification: {reason}"),
-            format!("Verification: {reason}"),
+        SimpleCheckResult::crit_with_details(
+            format!("Chain verification failed: {reason}"),
+            format!("Chain verification failed: {reason}"),
         )
     }
 }
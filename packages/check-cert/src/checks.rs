@@ -0,0 +1,145 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+//! Fetch the certificate of a server and run a set of checkers on it at once.
+
+use crate::check::{Collection, SimpleCheckResult};
+use crate::checker::{
+    certificate, chain, crl as crl_check, fetcher as fetcher_check, ocsp, protocol, verification,
+};
+use crate::fetcher::{self, CheckError, Config as FetcherConfig, Response, TlsVersion};
+use anyhow::Context;
+use std::path::PathBuf;
+use std::time::Duration;
+use typed_builder::TypedBuilder;
+
+/// Where the CRL comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Crl {
+    /// Download it from the distribution points of the leaf.
+    Fetch,
+    File(PathBuf),
+}
+
+/// The checkers to run, a checker without config is skipped.
+///
+/// `certificate` covers the validity, the hostname and the algorithms of the
+/// leaf, `chain` the expiry of the whole chain.
+#[derive(Debug, TypedBuilder)]
+#[builder(field_defaults(default))]
+pub struct CertChecks<'a> {
+    /// Defaults to a timeout of 10 seconds.
+    fetcher: Option<FetcherConfig>,
+    response: Option<fetcher_check::Config>,
+    protocol: Option<protocol::Config>,
+    verification: Option<verification::Config<'a>>,
+    chain: Option<chain::Config>,
+    ocsp: Option<ocsp::Config>,
+    crl: Option<Crl>,
+    certificate: Option<certificate::Config>,
+}
+
+/// CRIT for an error of the fetcher, with a hint if the handshake failed
+/// on a protocol older than `min_version`.
+pub fn fetch_error(err: CheckError, min_version: Option<TlsVersion>) -> SimpleCheckResult {
+    // One line with the causes, whatever `RUST_BACKTRACE` says.
+    match min_version {
+        Some(min_version) if err.is_protocol_mismatch() => SimpleCheckResult::crit(format!(
+            "{err:#} (the protocol was likely older than {min_version})"
+        )),
+        _ => SimpleCheckResult::crit(format!("{err:#}")),
+    }
+    .tag("connection")
+}
+
+/// Fetch the certificate of the server, a failure or an empty chain is CRIT.
+pub fn fetch(
+    server: &str,
+    port: u16,
+    config: FetcherConfig,
+    min_version: Option<TlsVersion>,
+) -> Result<Response, SimpleCheckResult> {
+    let response = fetcher::fetch_server_cert(server, port, config)
+        .map_err(|err| fetch_error(err, min_version))?;
+    if response.chain.is_empty() {
+        return Err(
+            SimpleCheckResult::crit("Empty or invalid certificate chain on host").tag("connection"),
+        );
+    }
+    Ok(response)
+}
+
+impl CertChecks<'_> {
+    fn fetcher_config(&self) -> FetcherConfig {
+        self.fetcher.clone().unwrap_or_else(|| {
+            FetcherConfig::builder()
+                .timeout(Some(Duration::from_secs(10)))
+                .build()
+        })
+    }
+
+    /// Nothing but the failure is reported if the certificate cannot be fetched.
+    pub fn run(self, server: &str, port: u16) -> Collection {
+        let min_version = self
+            .protocol
+            .as_ref()
+            .and_then(protocol::Config::min_version);
+        match fetch(server, port, self.fetcher_config(), min_version) {
+            Ok(response) => self.check(&response),
+            Err(err) => Collection::from(err),
+        }
+    }
+
+    /// Run the checkers on a response of the fetcher, its chain must not be empty.
+    pub fn check(mut self, response: &Response) -> Collection {
+        let mut collection = Collection::default();
+        if let Some(config) = self.response.take() {
+            collection.join(&mut fetcher_check::check(
+                response.timings,
+                response.attempts,
+                config,
+            ));
+        }
+        if let Some(config) = self.protocol.take() {
+            collection.join(&mut protocol::check(
+                response.protocol_version,
+                response.cipher.as_ref(),
+                config,
+            ));
+        }
+        collection.join(&mut self.check_chain(&response.chain, response.ocsp_staple.as_deref()));
+        collection
+    }
+
+    /// Run the checkers on a chain, fetched or not, that must not be empty.
+    /// `ocsp_staple` is the OCSP response stapled to the handshake.
+    pub fn check_chain(self, chain: &[Vec<u8>], ocsp_staple: Option<&[u8]>) -> Collection {
+        let fetcher_config = self.fetcher_config();
+        let mut collection = Collection::default();
+        if let Some(config) = self.verification {
+            collection.join(&mut verification::check(chain, config));
+        }
+        if let Some(config) = self.chain {
+            collection.join(&mut chain::check(chain, config));
+        }
+        if let Some(config) = self.ocsp {
+            collection.join(&mut ocsp::check(ocsp_staple, chain, config));
+        }
+        if let Some(crl) = self.crl {
+            let crl = match crl {
+                Crl::File(path) => {
+                    std::fs::read(&path).with_context(|| format!("Cannot read {}", path.display()))
+                }
+                Crl::Fetch => {
+                    fetcher::fetch_crl(&crl_check::distribution_points(&chain[0]), &fetcher_config)
+                }
+            };
+            collection.join(&mut crl_check::check(crl, chain));
+        }
+        if let Some(config) = self.certificate {
+            collection.join(&mut certificate::check(&chain[0], config));
+        }
+        collection
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+use crate::checker::certificate::{
+    dns_names, fingerprint, format_oid, pubkey_algorithm, pubkey_size,
+};
+use anyhow::{Context, Result};
+use time::format_description::well_known::Rfc3339;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str(r#"\""#),
+            '\\' => out.push_str(r"\\"),
+            '\n' => out.push_str(r"\n"),
+            '\r' => out.push_str(r"\r"),
+            '\t' => out.push_str(r"\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn quote_or_null(s: Option<&str>) -> String {
+    s.map_or(String::from("null"), quote)
+}
+
+fn format_time(time: ASN1Time) -> Result<String> {
+    Ok(time.to_datetime().format(&Rfc3339)?)
+}
+
+/// Render the parsed certificate as a JSON object.
+pub fn dump(der: &[u8]) -> Result<String> {
+    let (_rem, cert) = X509Certificate::from_der(der).context("Failed to parse certificate")?;
+
+    let alt_names = match cert.subject_alternative_name()? {
+        Some(ext) => dns_names(ext.value).map(quote).collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+    let pubkey = cert.public_key();
+    let fields = [
+        ("subject", quote(&cert.subject().to_string())),
+        ("issuer", quote(&cert.issuer().to_string())),
+        ("serial", quote(&cert.raw_serial_as_string())),
+        (
+            "not_before",
+            quote(&format_time(cert.validity().not_before)?),
+        ),
+        ("not_after", quote(&format_time(cert.validity().not_after)?)),
+        ("subject_alt_names", format!("[{}]", alt_names.join(", "))),
+        (
+            "signature_algorithm",
+            quote(&format_oid(&cert.signature_algorithm.algorithm)),
+        ),
+        (
+            "public_key_algorithm",
+            quote_or_null(pubkey_algorithm(pubkey).ok()),
+        ),
+        (
+            "public_key_size",
+            pubkey_size(pubkey).map_or(String::from("null"), |size| size.to_string()),
+        ),
+        ("sha256_fingerprint", quote(&fingerprint(der)?)),
+    ];
+    Ok(format!(
+        "{{\n{}\n}}",
+        fields
+            .iter()
+            .map(|(key, value)| format!("  {}: {value}", quote(key)))
+            .collect::<Vec<_>>()
+            .join(",\n")
+    ))
+}
+
+#[cfg(test)]
+mod test_quote {
+    use super::quote;
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(quote("CN=example.com"), r#""CN=example.com""#);
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(quote(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(quote("a\nb\tc"), r#""a\nb\tc""#);
+        assert_eq!(quote("\u{1}"), r#""\u0001""#);
+    }
+}
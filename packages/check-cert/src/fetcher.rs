@@ -2,43 +2,1510 @@
 // This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
 // conditions defined in the file COPYING, which is part of this source code package.
 
-use anyhow::{anyhow, Context, Result};
-use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
-use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
-use std::time::Duration;
+use anyhow::{anyhow, bail, Context, Result};
+use openssl::error::ErrorStack;
+use openssl::ssl::{
+    ErrorCode, HandshakeError, SslCipherRef, SslConnector, SslFiletype, SslMethod, SslVerifyMode,
+    SslVersion, StatusType,
+};
+use std::fmt::{Debug, Display, Formatter, Result as FormatResult};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use typed_builder::TypedBuilder;
 
-#[derive(Debug, TypedBuilder)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StartTls {
+    #[default]
+    None,
+    Smtp,
+    Imap,
+    Pop3,
+    Ftp,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AddrFamily {
+    Ipv4,
+    Ipv6,
+    #[default]
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    Ssl3,
+    Tls1_0,
+    Tls1_1,
+    Tls1_2,
+    Tls1_3,
+}
+
+impl Display for TlsVersion {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        match self {
+            Self::Ssl3 => write!(f, "SSL 3.0"),
+            Self::Tls1_0 => write!(f, "TLS 1.0"),
+            Self::Tls1_1 => write!(f, "TLS 1.1"),
+            Self::Tls1_2 => write!(f, "TLS 1.2"),
+            Self::Tls1_3 => write!(f, "TLS 1.3"),
+        }
+    }
+}
+
+impl TlsVersion {
+    fn from_ssl_version(version: SslVersion) -> Option<Self> {
+        match version {
+            SslVersion::SSL3 => Some(Self::Ssl3),
+            SslVersion::TLS1 => Some(Self::Tls1_0),
+            SslVersion::TLS1_1 => Some(Self::Tls1_1),
+            SslVersion::TLS1_2 => Some(Self::Tls1_2),
+            SslVersion::TLS1_3 => Some(Self::Tls1_3),
+            _ => None,
+        }
+    }
+}
+
+/// Cipher suite negotiated in the handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cipher {
+    /// Name as OpenSSL knows it, e.g., `ECDHE-RSA-AES128-GCM-SHA256`.
+    pub name: String,
+    /// Key exchange as in `openssl ciphers -v`, e.g., `ECDH` or `RSA`.
+    pub kx: Option<String>,
+    pub bits: u32,
+}
+
+impl Cipher {
+    fn from_ssl_cipher(cipher: &SslCipherRef) -> Self {
+        let description = cipher.description();
+        Self {
+            name: cipher.name().to_string(),
+            kx: description
+                .split_whitespace()
+                .find_map(|field| field.strip_prefix("Kx="))
+                .map(String::from),
+            bits: u32::try_from(cipher.bits().secret).unwrap_or_default(),
+        }
+    }
+}
+
+/// Why fetching the certificate failed.
+///
+/// `Display` and `Debug` are the ones of the wrapped error, so the output is
+/// the same whatever the cause.
+pub enum CheckError {
+    /// The server, or the proxy, could not be resolved.
+    Dns(anyhow::Error),
+    /// No TCP connection, or no tunnel through the proxy.
+    Connect(anyhow::Error),
+    /// The server did not connect or answer in time.
+    Timeout(anyhow::Error),
+    /// STARTTLS or the TLS handshake failed.
+    Handshake(anyhow::Error),
+    /// The client certificate or the certificates of the server could not be read.
+    Parse(anyhow::Error),
+    /// The client key does not match the client certificate.
+    Verify(anyhow::Error),
+}
+
+impl CheckError {
+    pub fn error(&self) -> &anyhow::Error {
+        match self {
+            Self::Dns(err)
+            | Self::Connect(err)
+            | Self::Timeout(err)
+            | Self::Handshake(err)
+            | Self::Parse(err)
+            | Self::Verify(err) => err,
+        }
+    }
+
+    fn context(self, context: String) -> Self {
+        match self {
+            Self::Dns(err) => Self::Dns(err.context(context)),
+            Self::Connect(err) => Self::Connect(err.context(context)),
+            Self::Timeout(err) => Self::Timeout(err.context(context)),
+            Self::Handshake(err) => Self::Handshake(err.context(context)),
+            Self::Parse(err) => Self::Parse(err.context(context)),
+            Self::Verify(err) => Self::Verify(err.context(context)),
+        }
+    }
+
+    /// Failures of the connection that may go away on retry: timeouts, refused or
+    /// reset connections and temporary resolver failures.  NXDOMAIN, TLS alerts or
+    /// a proxy denying the tunnel would only come back the same.
+    fn is_transient(&self) -> bool {
+        let root_cause = self.error().root_cause().to_string();
+        match self {
+            Self::Timeout(_) => true,
+            Self::Dns(_) => root_cause.ends_with("SERVFAIL"),
+            Self::Connect(_) | Self::Handshake(_) => [
+                "Connection refused",
+                "Connection reset by peer",
+                "Connection aborted",
+            ]
+            .iter()
+            .any(|failure| root_cause.starts_with(failure)),
+            Self::Parse(_) | Self::Verify(_) => false,
+        }
+    }
+
+    /// The handshake failed because client and server have no protocol version in
+    /// common: the server sent a `protocol_version` alert or OpenSSL refused the
+    /// version the server chose.
+    pub fn is_protocol_mismatch(&self) -> bool {
+        match self {
+            Self::Handshake(err) => {
+                let text = format!("{err:#}");
+                text.contains("TLS alert: protocol_version")
+                    || text.contains("unsupported protocol")
+                    || text.contains("no protocols available")
+            }
+            _ => false,
+        }
+    }
+
+    /// `Timeout` if an I/O operation of the error timed out, `otherwise` else.
+    fn classify(err: anyhow::Error, otherwise: fn(anyhow::Error) -> Self) -> Self {
+        // Reads time out with `WouldBlock` on Unix.
+        let timed_out = err.chain().any(|cause| {
+            cause.downcast_ref::<std::io::Error>().is_some_and(|err| {
+                matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock)
+            }) || matches!(
+                cause.downcast_ref::<HandshakeError<TcpStream>>(),
+                Some(HandshakeError::WouldBlock(_))
+            )
+        });
+        if timed_out {
+            Self::Timeout(err)
+        } else {
+            otherwise(err)
+        }
+    }
+}
+
+impl Display for CheckError {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        Display::fmt(self.error(), f)
+    }
+}
+
+impl Debug for CheckError {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        Debug::fmt(self.error(), f)
+    }
+}
+
+impl std::error::Error for CheckError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error().source()
+    }
+}
+
+/// HTTP proxy to tunnel through with `CONNECT`, given as `[http://]host:port`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proxy {
+    pub host: String,
+    pub port: u16,
+}
+
+impl FromStr for Proxy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let authority = match s.split_once("://") {
+            None => s,
+            Some(("http", rest)) => rest,
+            Some((scheme, _)) => bail!("Unsupported proxy scheme: {scheme}"),
+        };
+        let authority = authority.trim_end_matches('/');
+        let (host, port) = authority
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("Missing proxy port: {s}"))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            bail!("Missing proxy host: {s}")
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port: port
+                .parse()
+                .with_context(|| format!("Invalid proxy port: {s}"))?,
+        })
+    }
+}
+
+mod starttls {
+    use anyhow::{bail, Result};
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    fn read_line(reader: &mut impl BufRead) -> Result<String> {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            bail!("Connection closed by server during STARTTLS negotiation")
+        }
+        Ok(line.trim_end().to_string())
+    }
+
+    fn read_reply(reader: &mut impl BufRead) -> Result<String> {
+        // Multiline replies (RFC 5321, RFC 959) are `NNN-text` until the last `NNN text`.
+        loop {
+            let line = read_line(reader)?;
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok(line);
+            }
+        }
+    }
+
+    fn read_tagged(reader: &mut impl BufRead, tag: &str) -> Result<String> {
+        // Skip untagged IMAP responses (`* ...`).
+        loop {
+            let line = read_line(reader)?;
+            if line.starts_with(tag) {
+                return Ok(line);
+            }
+        }
+    }
+
+    fn send(mut stream: impl Write, command: &str) -> Result<()> {
+        stream.write_all(format!("{command}\r\n").as_bytes())?;
+        Ok(stream.flush()?)
+    }
+
+    fn expect_greeting(reply: String, prefix: &str) -> Result<()> {
+        if !reply.starts_with(prefix) {
+            bail!("Unexpected server greeting: {reply}")
+        }
+        Ok(())
+    }
+
+    fn expect_reply(reply: String, prefix: &str) -> Result<()> {
+        if !reply.starts_with(prefix) {
+            bail!("STARTTLS rejected: {reply}")
+        }
+        Ok(())
+    }
+
+    pub fn smtp<S>(stream: &S) -> Result<()>
+    where
+        for<'a> &'a S: Read + Write,
+    {
+        let mut reader = BufReader::new(stream);
+        expect_greeting(read_reply(&mut reader)?, "220")?;
+        send(stream, "EHLO localhost")?;
+        expect_greeting(read_reply(&mut reader)?, "250")?;
+        send(stream, "STARTTLS")?;
+        expect_reply(read_reply(&mut reader)?, "220")
+    }
+
+    pub fn imap<S>(stream: &S) -> Result<()>
+    where
+        for<'a> &'a S: Read + Write,
+    {
+        let mut reader = BufReader::new(stream);
+        expect_greeting(read_line(&mut reader)?, "* OK")?;
+        send(stream, "a1 STARTTLS")?;
+        expect_reply(read_tagged(&mut reader, "a1 ")?, "a1 OK")
+    }
+
+    pub fn pop3<S>(stream: &S) -> Result<()>
+    where
+        for<'a> &'a S: Read + Write,
+    {
+        let mut reader = BufReader::new(stream);
+        expect_greeting(read_line(&mut reader)?, "+OK")?;
+        send(stream, "STLS")?;
+        expect_reply(read_line(&mut reader)?, "+OK")
+    }
+
+    pub fn ftp<S>(stream: &S) -> Result<()>
+    where
+        for<'a> &'a S: Read + Write,
+    {
+        let mut reader = BufReader::new(stream);
+        expect_greeting(read_reply(&mut reader)?, "220")?;
+        send(stream, "AUTH TLS")?;
+        expect_reply(read_reply(&mut reader)?, "234")
+    }
+}
+
+/// Time spent in each phase of the fetch.
+///
+/// Behind a proxy, `dns_resolve` and `tcp_connect` are for the proxy and
+/// `proxy_connect` the `CONNECT` request.  `overall` then starts once the
+/// tunnel is established and only covers the endpoint.  Over a UNIX socket,
+/// there is neither `dns_resolve` nor `tcp_connect`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Timings {
+    pub overall: Duration,
+    pub dns_resolve: Option<Duration>,
+    pub tcp_connect: Option<Duration>,
+    pub proxy_connect: Option<Duration>,
+    pub starttls: Option<Duration>,
+    pub tls_handshake: Duration,
+}
+
+#[derive(Debug)]
+pub struct Response {
+    /// Address connected to, that is, the proxy if there is one, none over a UNIX socket.
+    pub addr: Option<SocketAddr>,
+    pub chain: Vec<Vec<u8>>,
+    pub ocsp_staple: Option<Vec<u8>>,
+    pub protocol_version: Option<TlsVersion>,
+    pub cipher: Option<Cipher>,
+    /// Where `GET /` redirects to, if asked for with `http_redirect`.
+    pub redirect: Option<String>,
+    pub timings: Timings,
+    pub attempts: Attempts,
+}
+
+/// Connection attempts made and allowed, starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attempts {
+    pub made: u8,
+    pub allowed: u8,
+}
+
+#[derive(Debug, Clone, TypedBuilder)]
 pub struct Config {
     timeout: Option<Duration>,
+    #[builder(default)]
+    connect_timeout: Option<Duration>,
+    #[builder(default)]
+    starttls: StartTls,
+    /// Connect to this address instead of resolving the server.
+    #[builder(default)]
+    connect_addr: Option<IpAddr>,
+    /// Handshake over this UNIX socket instead of TCP, the server is only the name.
+    #[builder(default)]
+    unix_socket: Option<PathBuf>,
+    /// Server name to send with SNI, defaults to the server.
+    #[builder(default)]
+    server_name: Option<String>,
+    #[builder(default)]
+    address_family: Option<AddrFamily>,
+    #[builder(default)]
+    client_cert: Option<PathBuf>,
+    #[builder(default)]
+    client_key: Option<PathBuf>,
+    #[builder(default)]
+    proxy: Option<Proxy>,
+    /// Connection attempts after the first one failed.
+    #[builder(default)]
+    retries: u8,
+    #[builder(default)]
+    retry_delay: Duration,
+    /// Send `GET /` after the handshake to find out about a redirect.
+    #[builder(default)]
+    http_redirect: bool,
 }
 
-fn to_addr(server: &str, port: u16) -> Result<SocketAddr> {
-    let mut addr_iter = format!("{server}:{port}").to_socket_addrs()?;
-    addr_iter.next().ok_or(anyhow!("no address"))
+fn resolver_error(message: &str) -> &str {
+    // The standard library only passes on the text of getaddrinfo(3).
+    if message.contains("Name or service not known")
+        || message.contains("nodename nor servname provided")
+        || message.contains("No address associated with hostname")
+    {
+        "NXDOMAIN"
+    } else if message.contains("Temporary failure in name resolution")
+        || message.contains("Non-recoverable failure in name resolution")
+    {
+        "SERVFAIL"
+    } else {
+        message
+    }
 }
 
-pub fn fetch_server_cert(server: &str, port: u16, config: Config) -> Result<Vec<Vec<u8>>> {
-    let addr = to_addr(server, port)?;
-    let stream = match config.timeout {
-        None => TcpStream::connect(addr)?,
-        Some(dur) => TcpStream::connect_timeout(&addr, dur)?,
+fn to_addrs(server: &str, port: u16, family: AddrFamily) -> Result<Vec<SocketAddr>> {
+    let addrs = (server, port)
+        .to_socket_addrs()
+        .map_err(|err| {
+            anyhow!(
+                "DNS resolution failed: {}",
+                resolver_error(&err.to_string())
+            )
+        })?
+        .collect::<Vec<_>>();
+    let first_of = |is_ipv4: bool| addrs.iter().find(|a| a.is_ipv4() == is_ipv4).copied();
+    let addrs = match family {
+        AddrFamily::Ipv4 => first_of(true).into_iter().collect::<Vec<_>>(),
+        AddrFamily::Ipv6 => first_of(false).into_iter().collect::<Vec<_>>(),
+        // Follow the resolver order, fall back to the other family.
+        AddrFamily::Auto => match addrs.first() {
+            None => vec![],
+            Some(addr) => [first_of(addr.is_ipv4()), first_of(!addr.is_ipv4())]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>(),
+        },
     };
+    (!addrs.is_empty()).then_some(addrs).ok_or(match family {
+        AddrFamily::Ipv4 => anyhow!("no IPv4 address"),
+        AddrFamily::Ipv6 => anyhow!("no IPv6 address"),
+        AddrFamily::Auto => anyhow!("no address"),
+    })
+}
+
+/// Name the failures of the TCP connection, the OS error numbers do not help.
+fn tcp_failure(err: &std::io::Error) -> Option<&'static str> {
+    match err.kind() {
+        ErrorKind::ConnectionRefused => Some("Connection refused"),
+        ErrorKind::ConnectionReset => Some("Connection reset by peer"),
+        ErrorKind::ConnectionAborted => Some("Connection aborted"),
+        ErrorKind::UnexpectedEof => Some("Connection closed by peer"),
+        _ => None,
+    }
+}
+
+fn connect(addr: &SocketAddr, timeout: Option<Duration>) -> Result<TcpStream, CheckError> {
+    match timeout {
+        None => TcpStream::connect(addr).map_err(|err| match tcp_failure(&err) {
+            Some(failure) => CheckError::Connect(anyhow!(failure)),
+            None => CheckError::classify(err.into(), CheckError::Connect),
+        }),
+        Some(dur) => TcpStream::connect_timeout(addr, dur).map_err(|err| match err.kind() {
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => {
+                CheckError::Timeout(anyhow!("Connection timed out after {dur:?}"))
+            }
+            _ => CheckError::Connect(tcp_failure(&err).map_or_else(|| err.into(), |f| anyhow!(f))),
+        }),
+    }
+}
+
+fn connect_any(
+    addrs: &[SocketAddr],
+    timeout: Option<Duration>,
+) -> Result<(SocketAddr, TcpStream), CheckError> {
+    let mut error = CheckError::Connect(anyhow!("no address"));
+    for addr in addrs {
+        match connect(addr, timeout) {
+            Ok(stream) => return Ok((*addr, stream)),
+            Err(err) => error = err,
+        }
+    }
+    Err(error)
+}
+
+fn authority(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+fn http_connect(mut stream: &TcpStream, server: &str, port: u16) -> Result<()> {
+    let authority = authority(server, port);
+    stream.write_all(
+        format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n\r\n").as_bytes(),
+    )?;
+    stream.flush()?;
+    let head = read_head(stream)?;
+    let status = head.lines().next().unwrap_or_default();
+    if !(status.starts_with("HTTP/1.") && status.split(' ').nth(1) == Some("200")) {
+        bail!("Proxy CONNECT failed: {status}")
+    }
+    Ok(())
+}
+
+/// Head of an HTTP response up to the empty line.  Reads byte by byte: the
+/// server may send the start of the TLS handshake right after it, which
+/// must stay in the stream.
+fn read_head(mut stream: impl Read) -> Result<String> {
+    let mut head = Vec::new();
+    let mut byte = [0u8];
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte)? == 0 {
+            bail!("Connection closed by proxy")
+        }
+        if head.len() == 16 * 1024 {
+            bail!("Proxy response header too long")
+        }
+        head.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+fn split_url(url: &str, scheme: &str, default_port: u16) -> Result<(String, u16, String)> {
+    let rest = url
+        .strip_prefix(scheme)
+        .and_then(|rest| rest.strip_prefix("://"))
+        .ok_or_else(|| anyhow!("Unsupported URL: {url}"))?;
+    // The authority ends at the path, the query or the fragment.
+    let (authority, rest) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    let path = match rest.split_once('#').map_or(rest, |(path, _fragment)| path) {
+        path if path.starts_with('/') => path.to_string(),
+        query => format!("/{query}"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (
+            host,
+            port.parse()
+                .with_context(|| format!("Invalid port: {url}"))?,
+        ),
+        _ => (authority, default_port),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        bail!("Missing host: {url}")
+    }
+    Ok((host.to_string(), port, path))
+}
+
+fn split_http_url(url: &str) -> Result<(String, u16, String)> {
+    split_url(url, "http", 80)
+}
+
+/// Host, port and path of an `https://` URL such as a redirect target.
+pub fn split_https_url(url: &str) -> Result<(String, u16, String)> {
+    split_url(url, "https", 443)
+}
+
+/// The `Location` of a redirect from the head of an HTTP response.
+fn read_redirect(reader: &mut impl BufRead) -> Option<String> {
+    let mut status = String::new();
+    reader.read_line(&mut status).ok()?;
+    if !status.split(' ').nth(1)?.starts_with('3') {
+        return None;
+    }
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).ok()? == 0 || line.trim_end().is_empty() {
+            return None;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("location") {
+                return Some(value.trim().to_string());
+            }
+        }
+    }
+}
+
+/// The `Host` of a request over HTTPS, with the port unless it is the default.
+fn https_host(host: &str, port: u16) -> String {
+    match port {
+        443 if host.contains(':') => format!("[{host}]"),
+        443 => host.to_string(),
+        port => authority(host, port),
+    }
+}
+
+/// How long to wait for the reply to the redirect probe, unless the timeout is shorter.
+const REDIRECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// GET `/` over the established TLS connection and tell where it redirects to.
+/// Any failure here only means that there is no redirect to follow.
+fn http_redirect(stream: &mut (impl Read + Write), host: &str) -> Option<String> {
+    stream
+        .write_all(
+            format!("GET / HTTP/1.0\r\nHost: {host}\r\nConnection: close\r\n\r\n").as_bytes(),
+        )
+        .ok()?;
+    // Only the head matters, do not read a large body.
+    read_redirect(&mut BufReader::new(stream.take(16 * 1024)))
+}
+
+fn http_get(url: &str, config: &Config) -> Result<Vec<u8>> {
+    let (host, port, path) = split_http_url(url)?;
+    // Proxies expect the absolute URL in the request line.
+    let (addrs, target) = match &config.proxy {
+        None => (
+            to_addrs(&host, port, config.address_family.unwrap_or_default())?,
+            path,
+        ),
+        Some(proxy) => (
+            to_addrs(
+                &proxy.host,
+                proxy.port,
+                config.address_family.unwrap_or_default(),
+            )?,
+            url.to_string(),
+        ),
+    };
+    let (_, mut stream) = connect_any(&addrs, config.connect_timeout.or(config.timeout))?;
     stream.set_read_timeout(config.timeout)?;
-    let mut connector_builder = SslConnector::builder(SslMethod::tls())?;
+    // HTTP/1.0 spares us chunked transfer encoding.
+    stream.write_all(
+        format!(
+            "GET {target} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            authority(&host, port)
+        )
+        .as_bytes(),
+    )?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    let pos = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| anyhow!("Invalid HTTP response from {url}"))?;
+    let head = String::from_utf8_lossy(&response[..pos]);
+    let status = head.lines().next().unwrap_or_default();
+    if status.split(' ').nth(1) != Some("200") {
+        bail!("GET {url} failed: {status}")
+    }
+    Ok(response.split_off(pos + 4))
+}
+
+/// Download the CRL from the first distribution point that answers.
+pub fn fetch_crl(urls: &[String], config: &Config) -> Result<Vec<u8>> {
+    let mut error = anyhow!("no HTTP distribution point");
+    for url in urls {
+        match http_get(url, config) {
+            Ok(crl) => return Ok(crl),
+            Err(err) => error = err,
+        }
+    }
+    Err(error)
+}
+
+fn negotiate_starttls<S>(stream: &S, starttls: StartTls) -> Result<()>
+where
+    for<'a> &'a S: Read + Write,
+{
+    match starttls {
+        StartTls::None => Ok(()),
+        StartTls::Smtp => starttls::smtp(stream),
+        StartTls::Imap => starttls::imap(stream),
+        StartTls::Pop3 => starttls::pop3(stream),
+        StartTls::Ftp => starttls::ftp(stream),
+    }
+}
+
+fn alert_from_reason(reason: &str) -> Option<String> {
+    // OpenSSL reports received alerts as, e.g., "sslv3 alert handshake failure".
+    reason
+        .split_once(" alert ")
+        .map(|(_, alert)| alert.replace(' ', "_"))
+}
+
+fn tls_alert(err: &openssl::ssl::Error) -> Option<String> {
+    err.ssl_error()?
+        .errors()
+        .iter()
+        .find_map(|e| e.reason().and_then(alert_from_reason))
+}
+
+/// The peer dropped the connection in the middle of the handshake, without an alert.
+fn tcp_failure_in_handshake(err: &openssl::ssl::Error) -> Option<&'static str> {
+    if let Some(io_error) = err.io_error() {
+        // Other errors, like a timeout, are classified with their own text.
+        return tcp_failure(io_error);
+    }
+    // OpenSSL 3 reports the EOF in the error stack, older versions as a bare SYSCALL.
+    let eof = err
+        .ssl_error()
+        .map_or(err.code() == ErrorCode::SYSCALL, |stack| {
+            stack
+                .errors()
+                .iter()
+                .any(|e| e.reason() == Some("unexpected eof while reading"))
+        });
+    eof.then_some("Connection closed by peer")
+}
+
+fn handshake_error<S>(err: HandshakeError<S>, with_client_cert: bool) -> CheckError
+where
+    S: Debug + Send + Sync + 'static,
+{
+    let (alert, tcp_failure) = match &err {
+        HandshakeError::Failure(mid) => (
+            tls_alert(mid.error()),
+            tcp_failure_in_handshake(mid.error()),
+        ),
+        // The read timeout of the stream expired.
+        HandshakeError::WouldBlock(_) => return CheckError::Timeout(err.into()),
+        HandshakeError::SetupFailure(_) => (None, None),
+    };
+    match alert.as_deref() {
+        Some(
+            alert @ ("handshake_failure"
+            | "bad_certificate"
+            | "certificate_required"
+            | "certificate_unknown"
+            | "certificate_expired"
+            | "certificate_revoked"
+            | "unsupported_certificate"
+            | "unknown_ca"
+            | "access_denied"),
+        ) if with_client_cert => {
+            CheckError::Handshake(anyhow!("Client authentication failed: TLS alert {alert}"))
+        }
+        Some(alert @ ("bad_certificate" | "certificate_required")) => CheckError::Handshake(
+            anyhow!("Server requires a client certificate: TLS alert {alert}"),
+        ),
+        Some(alert) => CheckError::Handshake(anyhow!("TLS alert: {alert}")),
+        None => match tcp_failure {
+            Some(failure) => CheckError::Handshake(anyhow!("{failure} during the TLS handshake")),
+            None => CheckError::classify(err.into(), CheckError::Handshake),
+        },
+    }
+}
+
+fn connector(config: &Config) -> Result<SslConnector, CheckError> {
+    let handshake = |err: ErrorStack| CheckError::Handshake(err.into());
+    let mut connector_builder = SslConnector::builder(SslMethod::tls()).map_err(handshake)?;
     connector_builder.set_verify(SslVerifyMode::NONE);
-    let connector = connector_builder.build();
-    connector
+    // Offer outdated protocols as well so that the checkers can report them.
+    connector_builder
+        .set_min_proto_version(None)
+        .map_err(handshake)?;
+    connector_builder.set_security_level(0);
+    if let Some(client_cert) = &config.client_cert {
+        // The key may be in the same PEM file as the certificate.
+        let client_key = config.client_key.as_ref().unwrap_or(client_cert);
+        connector_builder
+            .set_certificate_chain_file(client_cert)
+            .with_context(|| format!("Cannot load client certificate {}", client_cert.display()))
+            .map_err(CheckError::Parse)?;
+        connector_builder
+            .set_private_key_file(client_key, SslFiletype::PEM)
+            .with_context(|| format!("Cannot load client key {}", client_key.display()))
+            .map_err(CheckError::Parse)?;
+        connector_builder
+            .check_private_key()
+            .context("Client key does not match client certificate")
+            .map_err(CheckError::Verify)?;
+    }
+    Ok(connector_builder.build())
+}
+
+pub fn fetch_server_cert(server: &str, port: u16, config: Config) -> Result<Response, CheckError> {
+    // A broken client certificate will not get any better, only the connection is retried.
+    let connector = connector(&config)?;
+    let allowed = config.retries.saturating_add(1);
+    let mut made = 1;
+    loop {
+        match fetch_once(server, port, &config, &connector) {
+            Ok(mut response) => {
+                response.attempts = Attempts { made, allowed };
+                return Ok(response);
+            }
+            Err(err) if made < allowed && err.is_transient() => {
+                std::thread::sleep(config.retry_delay);
+                made += 1;
+            }
+            Err(err) if made > 1 => {
+                return Err(err.context(format!("Giving up after {made} attempts")))
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn fetch_once(
+    server: &str,
+    port: u16,
+    config: &Config,
+    connector: &SslConnector,
+) -> Result<Response, CheckError> {
+    if let Some(path) = &config.unix_socket {
+        return fetch_over_unix_socket(path, server, port, config, connector);
+    }
+    let mut start = Instant::now();
+    let addrs = match (&config.proxy, config.connect_addr) {
+        (None, Some(addr)) => vec![SocketAddr::new(addr, port)],
+        (None, None) => to_addrs(server, port, config.address_family.unwrap_or_default())
+            .map_err(CheckError::Dns)?,
+        (Some(proxy), _) => to_addrs(
+            &proxy.host,
+            proxy.port,
+            config.address_family.unwrap_or_default(),
+        )
+        .map_err(CheckError::Dns)?,
+    };
+    let dns_resolve = start.elapsed();
+    let phase = Instant::now();
+    let (addr, stream) = connect_any(&addrs, config.connect_timeout.or(config.timeout))?;
+    let tcp_connect = phase.elapsed();
+    stream
+        .set_read_timeout(config.timeout)
+        .map_err(|err| CheckError::Connect(err.into()))?;
+    let proxy_connect = match &config.proxy {
+        None => None,
+        Some(_) => {
+            let phase = Instant::now();
+            match config.connect_addr {
+                Some(addr) => http_connect(&stream, &addr.to_string(), port),
+                None => http_connect(&stream, server, port),
+            }
+            .map_err(|err| CheckError::classify(err, CheckError::Connect))?;
+            start = Instant::now();
+            Some(phase.elapsed())
+        }
+    };
+    let starttls = match config.starttls {
+        StartTls::None => None,
+        starttls => {
+            let phase = Instant::now();
+            negotiate_starttls(&stream, starttls)
+                .map_err(|err| CheckError::classify(err, CheckError::Handshake))?;
+            Some(phase.elapsed())
+        }
+    };
+    let tls = negotiate(stream, start, server, port, config, connector)?;
+    Ok(Response {
+        addr: Some(addr),
+        chain: tls.chain,
+        ocsp_staple: tls.ocsp_staple,
+        protocol_version: tls.protocol_version,
+        cipher: tls.cipher,
+        redirect: tls.redirect,
+        timings: Timings {
+            overall: tls.overall,
+            dns_resolve: Some(dns_resolve),
+            tcp_connect: Some(tcp_connect),
+            proxy_connect,
+            starttls,
+            tls_handshake: tls.tls_handshake,
+        },
+        attempts: Attempts {
+            made: 1,
+            allowed: 1,
+        },
+    })
+}
+
+fn fetch_over_unix_socket(
+    path: &Path,
+    server: &str,
+    port: u16,
+    config: &Config,
+    connector: &SslConnector,
+) -> Result<Response, CheckError> {
+    let start = Instant::now();
+    let stream = UnixStream::connect(path).map_err(|err| {
+        CheckError::Connect(match tcp_failure(&err) {
+            Some(failure) => anyhow!("Cannot connect to {}: {failure}", path.display()),
+            None => anyhow!("Cannot connect to {}: {err}", path.display()),
+        })
+    })?;
+    stream
+        .set_read_timeout(config.timeout)
+        .map_err(|err| CheckError::Connect(err.into()))?;
+    let starttls = match config.starttls {
+        StartTls::None => None,
+        starttls => {
+            let phase = Instant::now();
+            negotiate_starttls(&stream, starttls)
+                .map_err(|err| CheckError::classify(err, CheckError::Handshake))?;
+            Some(phase.elapsed())
+        }
+    };
+    let tls = negotiate(stream, start, server, port, config, connector)?;
+    Ok(Response {
+        addr: None,
+        chain: tls.chain,
+        ocsp_staple: tls.ocsp_staple,
+        protocol_version: tls.protocol_version,
+        cipher: tls.cipher,
+        redirect: tls.redirect,
+        timings: Timings {
+            overall: tls.overall,
+            dns_resolve: None,
+            tcp_connect: None,
+            proxy_connect: None,
+            starttls,
+            tls_handshake: tls.tls_handshake,
+        },
+        attempts: Attempts {
+            made: 1,
+            allowed: 1,
+        },
+    })
+}
+
+/// The transports to negotiate TLS over.
+trait Socket: Read + Write + Debug + Send + Sync + 'static {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+}
+
+impl Socket for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+impl Socket for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// What the TLS handshake yields, whatever the transport.
+struct Negotiated {
+    chain: Vec<Vec<u8>>,
+    ocsp_staple: Option<Vec<u8>>,
+    protocol_version: Option<TlsVersion>,
+    cipher: Option<Cipher>,
+    redirect: Option<String>,
+    tls_handshake: Duration,
+    overall: Duration,
+}
+
+fn negotiate<S: Socket>(
+    stream: S,
+    start: Instant,
+    server: &str,
+    port: u16,
+    config: &Config,
+    connector: &SslConnector,
+) -> Result<Negotiated, CheckError> {
+    let mut connect_config = connector
         .configure()
-        .context("Cannot configure connection")?;
-    let mut stream = connector.connect(server, stream)?;
+        .context("Cannot configure connection")
+        .map_err(CheckError::Handshake)?;
+    connect_config
+        .set_status_type(StatusType::OCSP)
+        .map_err(|err| CheckError::Handshake(err.into()))?;
+    let server_name = config.server_name.as_deref().unwrap_or(server);
+    let phase = Instant::now();
+    let mut stream = connect_config
+        .connect(server_name, stream)
+        .map_err(|err| handshake_error(err, config.client_cert.is_some()))?;
+    let tls_handshake = phase.elapsed();
     let chain = stream
         .ssl()
         .peer_cert_chain()
-        .context("Failed fetching peer cert chain")?
+        .context("Failed fetching peer cert chain")
+        .map_err(CheckError::Parse)?
         .iter()
         .flat_map(|x509| x509.to_der())
         .collect::<Vec<_>>();
-    stream.shutdown()?;
-    Ok(chain)
+    let ocsp_staple = stream.ssl().ocsp_status().map(<[u8]>::to_vec);
+    let protocol_version = stream
+        .ssl()
+        .version2()
+        .and_then(TlsVersion::from_ssl_version);
+    let cipher = stream.ssl().current_cipher().map(Cipher::from_ssl_cipher);
+    let overall = start.elapsed();
+    let redirect = if config.http_redirect {
+        // A server that does not speak HTTP would hold the check for the whole timeout.
+        let timeout = config
+            .timeout
+            .map_or(REDIRECT_TIMEOUT, |t| t.min(REDIRECT_TIMEOUT));
+        let redirect = match stream.get_ref().set_read_timeout(Some(timeout)) {
+            Ok(()) => http_redirect(&mut stream, &https_host(server_name, port)),
+            Err(_) => None,
+        };
+        // The server may well have closed the connection already.
+        let _ = stream.shutdown();
+        redirect
+    } else {
+        stream
+            .shutdown()
+            .map_err(|err| CheckError::classify(err.into(), CheckError::Connect))?;
+        None
+    };
+    Ok(Negotiated {
+        chain,
+        ocsp_staple,
+        protocol_version,
+        cipher,
+        redirect,
+        tls_handshake,
+        overall,
+    })
+}
+
+#[cfg(test)]
+mod test_check_error {
+    use super::{fetch_server_cert, CheckError, Config};
+    use anyhow::anyhow;
+    use std::io::Read;
+    use std::net::{Shutdown, TcpListener};
+    use std::time::Duration;
+
+    fn config() -> Config {
+        Config::builder()
+            .timeout(Some(Duration::from_millis(200)))
+            .build()
+    }
+
+    #[test]
+    fn test_protocol_mismatch() {
+        assert!(
+            CheckError::Handshake(anyhow!("TLS alert: protocol_version")).is_protocol_mismatch()
+        );
+        assert!(
+            !CheckError::Handshake(anyhow!("TLS alert: handshake_failure")).is_protocol_mismatch()
+        );
+        assert!(!CheckError::Connect(anyhow!("Connection refused")).is_protocol_mismatch());
+    }
+
+    #[test]
+    fn test_connect() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let err = fetch_server_cert("127.0.0.1", port, config()).unwrap_err();
+        assert!(matches!(err, CheckError::Connect(_)));
+        assert_eq!(err.to_string(), "Connection refused");
+    }
+
+    #[test]
+    fn test_timeout() {
+        // Connects through the backlog but never answers the handshake.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let err = fetch_server_cert("127.0.0.1", port, config()).unwrap_err();
+        assert!(matches!(err, CheckError::Timeout(_)));
+    }
+
+    #[test]
+    fn test_closed_in_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            // Read the ClientHello before closing: unread data would make
+            // the close a reset instead of an EOF.
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 4096]);
+            stream.shutdown(Shutdown::Write).unwrap();
+            // Until the client is done.
+            let _ = stream.read_to_end(&mut Vec::new());
+        });
+        let err = fetch_server_cert("127.0.0.1", port, config()).unwrap_err();
+        server.join().unwrap();
+        assert!(matches!(err, CheckError::Handshake(_)));
+        assert_eq!(
+            err.to_string(),
+            "Connection closed by peer during the TLS handshake"
+        );
+    }
+
+    #[test]
+    fn test_unix_socket() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("tls.sock");
+        let config = Config::builder()
+            .timeout(None)
+            .unix_socket(Some(path.clone()))
+            .build();
+        let err = fetch_server_cert("localhost", 443, config).unwrap_err();
+        assert!(matches!(err, CheckError::Connect(_)));
+        assert!(err
+            .to_string()
+            .starts_with(&format!("Cannot connect to {}: ", path.display())));
+    }
+
+    #[test]
+    fn test_dns() {
+        let err = fetch_server_cert("nonexistent.invalid", 443, config()).unwrap_err();
+        assert!(matches!(err, CheckError::Dns(_)));
+    }
+}
+
+#[cfg(test)]
+mod test_retries {
+    use super::{fetch_server_cert, Config};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn config() -> Config {
+        Config::builder()
+            .timeout(Some(Duration::from_millis(500)))
+            .retries(2)
+            .build()
+    }
+
+    #[test]
+    fn test_retry_refused() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let err = fetch_server_cert("127.0.0.1", port, config()).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err.error()),
+            "Giving up after 3 attempts: Connection refused"
+        );
+    }
+
+    #[test]
+    fn test_no_retry_on_handshake_failure() {
+        // Not TLS at all, this will not get any better.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = accepted.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.read(&mut [0; 4096]);
+                let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+            }
+        });
+        let err = fetch_server_cert("127.0.0.1", port, config()).unwrap_err();
+        assert!(!format!("{:#}", err.error()).starts_with("Giving up"));
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+}
+
+#[cfg(test)]
+mod test_resolver_error {
+    use super::resolver_error;
+
+    #[test]
+    fn test_resolver_error() {
+        assert_eq!(
+            resolver_error("failed to lookup address information: Name or service not known"),
+            "NXDOMAIN"
+        );
+        assert_eq!(
+            resolver_error(
+                "failed to lookup address information: Temporary failure in name resolution"
+            ),
+            "SERVFAIL"
+        );
+        assert_eq!(resolver_error("something else"), "something else");
+    }
+}
+
+#[cfg(test)]
+mod test_alert_from_reason {
+    use super::alert_from_reason;
+
+    #[test]
+    fn test_alerts() {
+        assert_eq!(
+            alert_from_reason("sslv3 alert handshake failure").as_deref(),
+            Some("handshake_failure")
+        );
+        assert_eq!(
+            alert_from_reason("tlsv13 alert certificate required").as_deref(),
+            Some("certificate_required")
+        );
+        assert_eq!(alert_from_reason("wrong version number"), None);
+    }
+}
+
+#[cfg(test)]
+mod test_to_addrs {
+    use super::{to_addrs, AddrFamily};
+
+    #[test]
+    fn test_ipv4() {
+        assert_eq!(
+            to_addrs("127.0.0.1", 443, AddrFamily::Auto).unwrap(),
+            vec!["127.0.0.1:443".parse().unwrap()]
+        );
+        assert!(to_addrs("127.0.0.1", 443, AddrFamily::Ipv6).is_err());
+    }
+
+    #[test]
+    fn test_ipv6() {
+        assert_eq!(
+            to_addrs("::1", 443, AddrFamily::Ipv6).unwrap(),
+            vec!["[::1]:443".parse().unwrap()]
+        );
+        assert_eq!(
+            to_addrs("::1", 443, AddrFamily::Ipv4)
+                .unwrap_err()
+                .to_string(),
+            "no IPv4 address"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_starttls {
+    use super::{negotiate_starttls, StartTls};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    fn serve(script: &'static [(&'static str, &'static str)]) -> TcpStream {
+        // Each entry is the expected command (empty for the greeting) and the reply.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            for (command, reply) in script {
+                if !command.is_empty() {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    assert_eq!(line.trim_end(), *command);
+                }
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+        TcpStream::connect(addr).unwrap()
+    }
+
+    #[test]
+    fn test_smtp() {
+        let stream = serve(&[
+            ("", "220 mail.example.com ESMTP\r\n"),
+            ("EHLO localhost", "250-mail.example.com\r\n250 STARTTLS\r\n"),
+            ("STARTTLS", "220 Ready to start TLS\r\n"),
+        ]);
+        assert!(negotiate_starttls(&stream, StartTls::Smtp).is_ok());
+    }
+
+    #[test]
+    fn test_smtp_rejected() {
+        let stream = serve(&[
+            ("", "220 mail.example.com ESMTP\r\n"),
+            ("EHLO localhost", "250 mail.example.com\r\n"),
+            ("STARTTLS", "454 TLS not available\r\n"),
+        ]);
+        assert_eq!(
+            negotiate_starttls(&stream, StartTls::Smtp)
+                .unwrap_err()
+                .to_string(),
+            "STARTTLS rejected: 454 TLS not available"
+        );
+    }
+
+    #[test]
+    fn test_imap() {
+        let stream = serve(&[
+            ("", "* OK IMAP4rev1 ready\r\n"),
+            ("a1 STARTTLS", "* NOTE untagged\r\na1 OK Begin TLS\r\n"),
+        ]);
+        assert!(negotiate_starttls(&stream, StartTls::Imap).is_ok());
+    }
+
+    #[test]
+    fn test_pop3_rejected() {
+        let stream = serve(&[("", "+OK POP3 ready\r\n"), ("STLS", "-ERR no TLS\r\n")]);
+        assert!(negotiate_starttls(&stream, StartTls::Pop3).is_err());
+    }
+
+    #[test]
+    fn test_ftp() {
+        let stream = serve(&[
+            ("", "220-Welcome\r\n220 FTP ready\r\n"),
+            ("AUTH TLS", "234 AUTH TLS OK\r\n"),
+        ]);
+        assert!(negotiate_starttls(&stream, StartTls::Ftp).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod test_proxy {
+    use super::{http_connect, Proxy};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    #[test]
+    fn test_parse() {
+        let proxy = |host: &str, port| Proxy {
+            host: String::from(host),
+            port,
+        };
+        assert_eq!(
+            "proxy.example.com:3128".parse::<Proxy>().unwrap(),
+            proxy("proxy.example.com", 3128)
+        );
+        assert_eq!(
+            "http://proxy.example.com:8080/".parse::<Proxy>().unwrap(),
+            proxy("proxy.example.com", 8080)
+        );
+        assert_eq!(
+            "http://[::1]:3128".parse::<Proxy>().unwrap(),
+            proxy("::1", 3128)
+        );
+        assert!("proxy.example.com".parse::<Proxy>().is_err());
+        assert!("https://proxy.example.com:443".parse::<Proxy>().is_err());
+        assert!(":3128".parse::<Proxy>().is_err());
+    }
+
+    fn serve(reply: &'static str) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "CONNECT example.com:443 HTTP/1.1\r\n");
+            while line.trim_end() != "" {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+            }
+            stream.write_all(reply.as_bytes()).unwrap();
+        });
+        TcpStream::connect(addr).unwrap()
+    }
+
+    #[test]
+    fn test_connect() {
+        let stream = serve("HTTP/1.1 200 Connection established\r\nVia: proxy\r\n\r\n");
+        assert!(http_connect(&stream, "example.com", 443).is_ok());
+    }
+
+    #[test]
+    fn test_connect_keeps_tunnel_data() {
+        // The first bytes through the tunnel arrive with the reply.
+        let mut stream = serve("HTTP/1.1 200 Connection established\r\n\r\n\x16\x03\x03");
+        assert!(http_connect(&stream, "example.com", 443).is_ok());
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"\x16\x03\x03");
+    }
+
+    #[test]
+    fn test_connect_closed() {
+        let stream = serve("HTTP/1.1 200 Connection established\r\n");
+        assert_eq!(
+            http_connect(&stream, "example.com", 443)
+                .unwrap_err()
+                .to_string(),
+            "Connection closed by proxy"
+        );
+    }
+
+    #[test]
+    fn test_connect_rejected() {
+        let stream = serve("HTTP/1.1 407 Proxy Authentication Required\r\n\r\n");
+        assert_eq!(
+            http_connect(&stream, "example.com", 443)
+                .unwrap_err()
+                .to_string(),
+            "Proxy CONNECT failed: HTTP/1.1 407 Proxy Authentication Required"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_http {
+    use super::{fetch_crl, https_host, read_redirect, split_http_url, split_https_url, Config};
+    use std::io::{BufRead, BufReader, Cursor, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_split_http_url() {
+        let split = |url| split_http_url(url).unwrap();
+        assert_eq!(
+            split("http://crl.example.com/ca.crl"),
+            (String::from("crl.example.com"), 80, String::from("/ca.crl"))
+        );
+        assert_eq!(
+            split("http://crl.example.com:8080"),
+            (String::from("crl.example.com"), 8080, String::from("/"))
+        );
+        assert_eq!(
+            split("http://[::1]/ca.crl"),
+            (String::from("::1"), 80, String::from("/ca.crl"))
+        );
+        assert!(split_http_url("https://crl.example.com/ca.crl").is_err());
+        assert!(split_http_url("ldap://crl.example.com/cn=ca").is_err());
+    }
+
+    #[test]
+    fn test_split_https_url() {
+        assert_eq!(
+            split_https_url("https://www.example.com/index.html").unwrap(),
+            (
+                String::from("www.example.com"),
+                443,
+                String::from("/index.html")
+            )
+        );
+        assert_eq!(
+            split_https_url("https://www.example.com:8443").unwrap(),
+            (String::from("www.example.com"), 8443, String::from("/"))
+        );
+        assert_eq!(
+            split_https_url("https://www.example.com?lang=en").unwrap(),
+            (
+                String::from("www.example.com"),
+                443,
+                String::from("/?lang=en")
+            )
+        );
+        assert_eq!(
+            split_https_url("https://www.example.com:8443#top").unwrap(),
+            (String::from("www.example.com"), 8443, String::from("/"))
+        );
+        assert_eq!(
+            split_https_url("https://www.example.com/a?b=c#d").unwrap(),
+            (String::from("www.example.com"), 443, String::from("/a?b=c"))
+        );
+        assert!(split_https_url("http://www.example.com/").is_err());
+        assert!(split_https_url("/index.html").is_err());
+    }
+
+    #[test]
+    fn test_https_host() {
+        assert_eq!(https_host("www.example.com", 443), "www.example.com");
+        assert_eq!(https_host("www.example.com", 8443), "www.example.com:8443");
+        assert_eq!(https_host("::1", 443), "[::1]");
+        assert_eq!(https_host("::1", 8443), "[::1]:8443");
+    }
+
+    #[test]
+    fn test_read_redirect() {
+        let read = |head: &str| read_redirect(&mut Cursor::new(head));
+        assert_eq!(
+            read("HTTP/1.1 301 Moved Permanently\r\nServer: x\r\nlocation: https://www.example.com/\r\n\r\n"),
+            Some(String::from("https://www.example.com/"))
+        );
+        assert_eq!(
+            read("HTTP/1.1 200 OK\r\nLocation: /elsewhere\r\n\r\n"),
+            None
+        );
+        assert_eq!(read("HTTP/1.1 302 Found\r\n\r\nLocation: /body\r\n"), None);
+        assert_eq!(read("garbage"), None);
+        assert_eq!(read(""), None);
+    }
+
+    fn serve(reply: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "GET /ca.crl HTTP/1.0\r\n");
+            while line.trim_end() != "" {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+            }
+            stream.write_all(reply).unwrap();
+        });
+        format!("http://{addr}/ca.crl")
+    }
+
+    #[test]
+    fn test_fetch_crl() {
+        let url = serve(b"HTTP/1.0 200 OK\r\nContent-Type: application/pkix-crl\r\n\r\n\x30\x00");
+        assert_eq!(
+            fetch_crl(&[url], &Config::builder().timeout(None).build()).unwrap(),
+            b"\x30\x00"
+        );
+    }
+
+    #[test]
+    fn test_fetch_crl_not_found() {
+        let url = serve(b"HTTP/1.0 404 Not Found\r\n\r\n");
+        assert_eq!(
+            fetch_crl(&[url.clone()], &Config::builder().timeout(None).build())
+                .unwrap_err()
+                .to_string(),
+            format!("GET {url} failed: HTTP/1.0 404 Not Found")
+        );
+    }
+
+    #[test]
+    fn test_no_distribution_point() {
+        assert!(fetch_crl(&[], &Config::builder().timeout(None).build()).is_err());
+    }
 }
@@ -3,11 +3,19 @@
 // conditions defined in the file COPYING, which is part of this source code package.
 
 pub mod check;
+pub mod checks;
 pub mod checker {
+    pub mod baseline;
     pub mod certificate;
+    pub mod chain;
+    pub mod crl;
     pub mod fetcher;
     pub mod info;
+    pub mod ocsp;
+    pub mod protocol;
     pub mod verification;
 }
+pub mod dump;
 pub mod fetcher;
+pub mod messages;
 pub mod truststore;
@@ -3,17 +3,32 @@
 // conditions defined in the file COPYING, which is part of this source code package.
 
 use anyhow::Result;
-use check_cert::check::{self, Levels, LevelsChecker, LevelsStrategy};
-use check_cert::checker::certificate::{self, Config as CertChecks};
-use check_cert::checker::fetcher::{self as fetcher_check, Config as FetcherChecks};
+use check_cert::check::{
+    self, Collection, Levels, LevelsChecker, LevelsStrategy, SimpleCheckResult,
+};
+use check_cert::checker::baseline::{self, Config as BaselineChecks, Renewal, Snapshot};
+use check_cert::checker::certificate::{self, Config as CertChecks, MissingEku, MissingSan};
+use check_cert::checker::chain::Config as ChainChecks;
+use check_cert::checker::fetcher::Config as FetcherChecks;
 use check_cert::checker::info::{self, Config as InfoConfig};
-use check_cert::checker::verification::{self, Config as VerifChecks};
-use check_cert::fetcher::{self, Config as FetcherConfig};
-use check_cert::truststore;
-use clap::{Parser, ValueEnum};
+use check_cert::checker::ocsp::{Config as OcspChecks, MissingStaple};
+use check_cert::checker::protocol::Config as ProtocolChecks;
+use check_cert::checker::verification::Config as VerifChecks;
+use check_cert::checks::{self, Crl};
+use check_cert::dump;
+use check_cert::fetcher::{
+    self, split_https_url, AddrFamily, Config as FetcherConfig, Proxy, StartTls, TlsVersion,
+};
+use check_cert::messages::{Language, TimeUnit};
+use check_cert::truststore::{self, CertFormat};
+use clap::{error::ErrorKind, Parser, ValueEnum};
+use std::path::Path;
 use std::time::Duration as StdDuration;
-use std::time::Instant;
-use time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, Duration, OffsetDateTime};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
 
 #[allow(non_camel_case_types)]
 #[allow(clippy::upper_case_acronyms)]
@@ -40,6 +55,179 @@ impl ClapPubKeyAlgorithm {
     }
 }
 
+#[derive(Debug, Clone, ValueEnum)]
+enum ClapStartTls {
+    Smtp,
+    Imap,
+    Pop3,
+    Ftp,
+}
+
+impl From<ClapStartTls> for StartTls {
+    fn from(starttls: ClapStartTls) -> Self {
+        match starttls {
+            ClapStartTls::Smtp => Self::Smtp,
+            ClapStartTls::Imap => Self::Imap,
+            ClapStartTls::Pop3 => Self::Pop3,
+            ClapStartTls::Ftp => Self::Ftp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ClapAddrFamily {
+    Ipv4,
+    Ipv6,
+    Auto,
+}
+
+impl From<ClapAddrFamily> for AddrFamily {
+    fn from(family: ClapAddrFamily) -> Self {
+        match family {
+            ClapAddrFamily::Ipv4 => Self::Ipv4,
+            ClapAddrFamily::Ipv6 => Self::Ipv6,
+            ClapAddrFamily::Auto => Self::Auto,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ClapMissingStaple {
+    Ok,
+    Warn,
+    Crit,
+}
+
+impl From<ClapMissingStaple> for MissingStaple {
+    fn from(missing_staple: ClapMissingStaple) -> Self {
+        match missing_staple {
+            ClapMissingStaple::Ok => Self::Ok,
+            ClapMissingStaple::Warn => Self::Warn,
+            ClapMissingStaple::Crit => Self::Crit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ClapRenewal {
+    Accept,
+    Warn,
+}
+
+impl From<ClapRenewal> for Renewal {
+    fn from(renewal: ClapRenewal) -> Self {
+        match renewal {
+            ClapRenewal::Accept => Self::Accept,
+            ClapRenewal::Warn => Self::Warn,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ClapCertFormat {
+    Pem,
+    Der,
+    Pkcs7,
+}
+
+impl From<ClapCertFormat> for CertFormat {
+    fn from(format: ClapCertFormat) -> Self {
+        match format {
+            ClapCertFormat::Pem => Self::Pem,
+            ClapCertFormat::Der => Self::Der,
+            ClapCertFormat::Pkcs7 => Self::Pkcs7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ClapMissingSan {
+    Warn,
+    Crit,
+}
+
+impl From<ClapMissingSan> for MissingSan {
+    fn from(missing_san: ClapMissingSan) -> Self {
+        match missing_san {
+            ClapMissingSan::Warn => Self::Warn,
+            ClapMissingSan::Crit => Self::Crit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ClapMissingEku {
+    Any,
+    Warn,
+    Crit,
+}
+
+impl From<ClapMissingEku> for MissingEku {
+    fn from(missing_eku: ClapMissingEku) -> Self {
+        match missing_eku {
+            ClapMissingEku::Any => Self::Any,
+            ClapMissingEku::Warn => Self::Warn,
+            ClapMissingEku::Crit => Self::Crit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ClapTimeUnit {
+    S,
+    Ms,
+}
+
+impl From<ClapTimeUnit> for TimeUnit {
+    fn from(unit: ClapTimeUnit) -> Self {
+        match unit {
+            ClapTimeUnit::S => Self::Seconds,
+            ClapTimeUnit::Ms => Self::Milliseconds,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ClapTlsVersion {
+    #[value(name = "ssl3.0")]
+    Ssl3,
+    #[value(name = "tls1.0")]
+    Tls1_0,
+    #[value(name = "tls1.1")]
+    Tls1_1,
+    #[value(name = "tls1.2")]
+    Tls1_2,
+    #[value(name = "tls1.3")]
+    Tls1_3,
+}
+
+impl From<ClapTlsVersion> for TlsVersion {
+    fn from(version: ClapTlsVersion) -> Self {
+        match version {
+            ClapTlsVersion::Ssl3 => Self::Ssl3,
+            ClapTlsVersion::Tls1_0 => Self::Tls1_0,
+            ClapTlsVersion::Tls1_1 => Self::Tls1_1,
+            ClapTlsVersion::Tls1_2 => Self::Tls1_2,
+            ClapTlsVersion::Tls1_3 => Self::Tls1_3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ClapLanguage {
+    En,
+    De,
+}
+
+impl From<ClapLanguage> for Language {
+    fn from(language: ClapLanguage) -> Self {
+        match language {
+            ClapLanguage::En => Self::En,
+            ClapLanguage::De => Self::De,
+        }
+    }
+}
+
 fn parse_levels<F, T1, T2, U>(strat: LevelsStrategy, lvl: Vec<T1>, mut conv: F) -> LevelsChecker<U>
 where
     T1: std::fmt::Debug,
@@ -55,29 +243,121 @@ where
     lvl_chk
 }
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(about = "check_cert")]
 struct Args {
     /// URL to check
-    #[arg(short, long)]
-    url: String,
+    #[arg(short, long, required_unless_present = "cert_file")]
+    url: Option<String>,
 
     /// Port
     #[arg(short, long, default_value_t = 443)]
     port: u16,
 
+    /// Further HOST[:PORT] to check concurrently, may be repeated, each target is
+    /// verified against its own host with --verify-hostname
+    #[arg(long = "target", value_parser = parse_target, conflicts_with = "dump_cert")]
+    targets: Vec<(String, u16)>,
+
+    /// Check the certificates (PEM, DER or PKCS#7) in this file instead of fetching them
+    #[arg(long, conflicts_with_all = ["url", "targets"])]
+    cert_file: Option<std::path::PathBuf>,
+
+    /// Format of --cert-file, detected from the content by default
+    #[arg(long, value_enum, requires = "cert_file")]
+    cert_format: Option<ClapCertFormat>,
+
+    /// Index (from 0) of the leaf among the certificates of --cert-file, found by
+    /// --validation-host or as the first non-CA certificate otherwise
+    #[arg(long, requires = "cert_file")]
+    leaf_index: Option<usize>,
+
+    /// Unit of the response times in both text and metrics, defaults to ms in the text and s in the metrics
+    #[arg(long, value_enum)]
+    time_unit: Option<ClapTimeUnit>,
+
+    /// Language of the response time, connection attempt, expiry and subject/issuer
+    /// messages, the other messages are in English
+    #[arg(long, value_enum, env = "CHECK_CERT_LANGUAGE", default_value_t = ClapLanguage::En)]
+    language: ClapLanguage,
+
     /// Verbose output
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
 
-    /// Set timeout in seconds
+    /// Print only the summary line with the metrics, without the details
+    #[arg(long, default_value_t = false, action = clap::ArgAction::SetTrue)]
+    compact: bool,
+
+    /// Prefix the lines of the sub-checks with a stable [tag], like [expiry] or [ocsp], for event rules
+    #[arg(long, default_value_t = false, action = clap::ArgAction::SetTrue)]
+    tags: bool,
+
+    /// Set timeout in seconds for the connection, the handshake and each read
     #[arg(long, default_value_t = 10)]
     timeout: u64,
 
+    /// Abort with UNKNOWN if the whole check, all targets and redirects included,
+    /// takes longer than this many seconds
+    #[arg(long)]
+    overall_timeout: Option<u64>,
+
+    /// Set connection timeout in seconds, defaults to --timeout
+    #[arg(long)]
+    connect_timeout: Option<u64>,
+
+    /// Retry failed connections and TLS handshakes this many times
+    #[arg(long, default_value_t = 0)]
+    retries: u8,
+
+    /// Delay between retries in seconds
+    #[arg(long, default_value_t = 1)]
+    retry_delay: u64,
+
+    /// Address family to connect with, `auto` falls back to the other family
+    #[arg(long)]
+    address_family: Option<ClapAddrFamily>,
+
+    /// Server name to send with SNI, defaults to the URL
+    #[arg(long, conflicts_with = "targets")]
+    server_name: Option<String>,
+
+    /// Connect to this IP address instead of resolving the URL
+    #[arg(long, conflicts_with = "targets")]
+    connect_addr: Option<std::net::IpAddr>,
+
+    /// Handshake over this UNIX socket instead of TCP, the URL is then only the server name
+    #[arg(long, conflicts_with_all = ["targets", "connect_addr", "proxy"])]
+    unix_socket: Option<std::path::PathBuf>,
+
+    /// Verify that the subject alternative names cover this name instead of the URL
+    #[arg(long, conflicts_with = "targets")]
+    validation_host: Option<String>,
+
+    /// Client certificate (PEM) to present, may contain the key as well
+    #[arg(long)]
+    client_cert: Option<std::path::PathBuf>,
+
+    /// Client key (PEM), defaults to the client certificate file
+    #[arg(long, requires = "client_cert")]
+    client_key: Option<std::path::PathBuf>,
+
+    /// HTTP proxy to connect through with CONNECT, as [http://]HOST:PORT
+    #[arg(long)]
+    proxy: Option<Proxy>,
+
+    /// Negotiate TLS with STARTTLS for the given protocol
+    #[arg(long)]
+    starttls: Option<ClapStartTls>,
+
     /// Expected serial
     #[arg(long)]
     serial: Option<String>,
 
+    /// Expected SHA-256 fingerprint, with or without colons
+    #[arg(long)]
+    fingerprint: Option<String>,
+
     /// Expected subject common name (CN)
     #[arg(long)]
     subject_cn: Option<String>,
@@ -86,6 +366,36 @@ struct Args {
     #[arg(long, num_args = 0..)]
     subject_alt_names: Option<Vec<String>>,
 
+    /// Names the subject alternative names must cover, wildcards included
+    #[arg(long, num_args = 1..)]
+    required_san: Option<Vec<String>>,
+
+    /// Minimum number of labels after the wildcard of a subject alternative name, 0 to
+    /// allow any. Only the labels are counted, the public suffix list is not consulted:
+    /// "*.co.uk" passes the default of 2, use 3 for such suffixes
+    #[arg(long, default_value_t = 2)]
+    wildcard_min_labels: usize,
+
+    /// State if some of the required subject alternative names are missing
+    #[arg(long, value_enum, default_value_t = ClapMissingSan::Warn)]
+    missing_san: ClapMissingSan,
+
+    /// Required extended key usages (name like `serverAuth` or OID)
+    #[arg(long, num_args = 0..)]
+    required_eku: Option<Vec<String>>,
+
+    /// State if the certificate has no extended key usage, that is, any usage
+    #[arg(long, value_enum, default_value_t = ClapMissingEku::Any)]
+    missing_eku: ClapMissingEku,
+
+    /// Verify that the subject alternative names cover the URL
+    #[arg(long, default_value_t = false, action = clap::ArgAction::SetTrue)]
+    verify_hostname: bool,
+
+    /// Warn if the certificate is not for the SNI name, that is, likely the default one of the server
+    #[arg(long, default_value_t = false, action = clap::ArgAction::SetTrue)]
+    detect_default_cert: bool,
+
     /// Expected subject organization (O)
     #[arg(long)]
     subject_o: Option<String>,
@@ -98,6 +408,10 @@ struct Args {
     #[arg(long)]
     issuer_cn: Option<String>,
 
+    /// Expected issuer DN, e.g. "C=US, O=Let's Encrypt, CN=R3", compared regardless of order, case and whitespace
+    #[arg(long)]
+    issuer: Option<String>,
+
     /// Expected issuer organization (O)
     #[arg(long)]
     issuer_o: Option<String>,
@@ -118,6 +432,10 @@ struct Args {
     #[arg(long)]
     signature_algorithm: Option<String>,
 
+    /// Forbidden signature algorithms (OID or name), defaults to MD2, MD5, and SHA-1 based ones
+    #[arg(long, num_args = 0.., default_values = certificate::WEAK_SIGNATURE_ALGORITHMS)]
+    forbidden_signature_algorithms: Option<Vec<String>>,
+
     /// Expected public key algorithm
     #[arg(long)]
     pubkey_algorithm: Option<ClapPubKeyAlgorithm>,
@@ -126,13 +444,43 @@ struct Args {
     #[arg(long)]
     pubkey_size: Option<usize>,
 
+    /// Public key size levels in bits, keys other than RSA and DSA use the RSA size
+    /// of their security strength, e.g., 3072 for P-256 and Ed25519 or 7680 for Ed448 [WARN CRIT]
+    #[arg(long, num_args = 2)]
+    pubkey_bits: Option<Vec<u32>>,
+
+    /// Minimum number of embedded signed certificate timestamps [WARN CRIT]
+    #[arg(long, num_args = 2)]
+    sct_count: Option<Vec<u32>>,
+
     /// Certificate expiration levels in seconds [WARN CRIT]
     #[arg(long, num_args = 2, default_values_t = [30 * 24 * 3600, 0])]
     not_after: Vec<u32>,
 
+    /// Warn if the certificate expires before this date (RFC 3339, or YYYY-MM-DD for midnight UTC)
+    #[arg(long, value_parser = parse_deadline)]
+    warn_deadline: Option<OffsetDateTime>,
+
+    /// Crit if the certificate expires before this date (RFC 3339, or YYYY-MM-DD for midnight UTC)
+    #[arg(long, value_parser = parse_deadline)]
+    crit_deadline: Option<OffsetDateTime>,
+
+    /// Clock skew in seconds to allow before a certificate is valid
+    #[arg(long, default_value_t = 300)]
+    not_before_skew: u32,
+
+    /// Certificate age levels in seconds since issuance [WARN CRIT]
+    #[arg(long, num_args = 2)]
+    cert_age: Option<Vec<u32>>,
+
+    /// Apply the expiration levels to the certificate of the chain that expires first
+    #[arg(long, default_value_t = false, action = clap::ArgAction::SetTrue)]
+    check_chain_expiry: bool,
+
     /// Max allowed validity (difference between not_before and not_after, in days)
-    #[arg(long)]
-    max_validity: Option<u32>,
+    /// [WARN [CRIT]], without CRIT it only warns
+    #[arg(long, num_args = 1..=2)]
+    max_validity: Option<Vec<u32>>,
 
     /// Overall response time levels in seconds [WARN CRIT]
     #[arg(
@@ -146,9 +494,41 @@ struct Args {
     #[arg(long)]
     ca_store: Option<std::path::PathBuf>,
 
+    /// Minimum protocol version the server must negotiate
+    #[arg(long)]
+    min_tls_version: Option<ClapTlsVersion>,
+
+    /// State if the server does not staple an OCSP response
+    #[arg(long, value_enum, default_value_t = ClapMissingStaple::Ok)]
+    ocsp_missing_staple: ClapMissingStaple,
+
+    /// Check the revocation list from the CRL distribution points of the certificate
+    #[arg(long, default_value_t = false, action = clap::ArgAction::SetTrue)]
+    check_crl: bool,
+
+    /// Check against this revocation list (DER or PEM) instead of downloading it
+    #[arg(long)]
+    crl_file: Option<std::path::PathBuf>,
+
+    /// Compare the certificate with the snapshot in this file, save one if there is none
+    #[arg(long, conflicts_with = "targets")]
+    baseline: Option<std::path::PathBuf>,
+
+    /// State if only the validity and the serial changed since the baseline, not the key
+    #[arg(long, value_enum, default_value_t = ClapRenewal::Accept)]
+    baseline_renewal: ClapRenewal,
+
     /// Allow self-signed certificates
     #[arg(long, default_value_t = false, action = clap::ArgAction::SetTrue)]
     allow_self_signed: bool,
+
+    /// Print the parsed server certificate as JSON instead of checking it
+    #[arg(long, default_value_t = false, action = clap::ArgAction::SetTrue)]
+    dump_cert: bool,
+
+    /// Follow up to this many HTTPS redirects of GET / and check the certificates there too
+    #[arg(long, default_value_t = 0, conflicts_with_all = ["cert_file", "starttls", "dump_cert"])]
+    follow_redirects: u8,
 }
 
 fn verbose(verbosity: u8, level: u8, header: &str, text: &str) {
@@ -164,105 +544,506 @@ fn to_pem(der: &[u8]) -> Vec<u8> {
         .unwrap()
 }
 
+fn parse_target(target: &str) -> Result<(String, u16), String> {
+    let (host, port) = match target.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (
+            host,
+            port.parse()
+                .map_err(|_| format!("invalid port in {target}"))?,
+        ),
+        _ => (target, 443),
+    };
+    Ok((
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string(),
+        port,
+    ))
+}
+
+fn parse_deadline(deadline: &str) -> Result<OffsetDateTime, String> {
+    // A plain date is midnight UTC.
+    OffsetDateTime::parse(deadline, &Rfc3339)
+        .or_else(|_| {
+            Date::parse(deadline, format_description!("[year]-[month]-[day]"))
+                .map(|date| date.midnight().assume_utc())
+        })
+        .map_err(|_| format!("invalid RFC 3339 date or datetime: {deadline}"))
+}
+
+fn fetcher_config(args: &Args) -> FetcherConfig {
+    FetcherConfig::builder()
+        .timeout((args.timeout != 0).then_some(StdDuration::new(args.timeout, 0)))
+        .connect_timeout(args.connect_timeout.map(StdDuration::from_secs))
+        .starttls(args.starttls.clone().map_or(StartTls::None, StartTls::from))
+        .connect_addr(args.connect_addr)
+        .unix_socket(args.unix_socket.clone())
+        .server_name(args.server_name.clone())
+        .address_family(args.address_family.clone().map(AddrFamily::from))
+        .client_cert(args.client_cert.clone())
+        .client_key(args.client_key.clone())
+        .proxy(args.proxy.clone())
+        .retries(args.retries)
+        .retry_delay(StdDuration::from_secs(args.retry_delay))
+        .http_redirect(args.follow_redirects != 0)
+        .build()
+}
+
+fn fetch(args: &Args, server: &str, port: u16) -> Result<fetcher::Response, SimpleCheckResult> {
+    let min_tls_version = args.min_tls_version.clone().map(TlsVersion::from);
+    let response = checks::fetch(server, port, fetcher_config(args), min_tls_version)?;
+    verbose(
+        args.verbose,
+        1,
+        "INFO: ",
+        &format!(
+            "received chain of {} certificates from host",
+            response.chain.len()
+        ),
+    );
+    Ok(response)
+}
+
+/// The chain from the file with the leaf first, and which certificate the leaf is
+/// if there was a choice.
+fn load(args: &Args, path: &Path) -> Result<(Vec<Vec<u8>>, Option<String>), SimpleCheckResult> {
+    let mut chain = truststore::load_certs(path, args.cert_format.clone().map(CertFormat::from))
+        .map_err(|err| SimpleCheckResult::unknown(format!("{err:#}")).tag("cert_file"))?;
+    verbose(
+        args.verbose,
+        1,
+        "INFO: ",
+        &format!("loaded chain of {} certificates from file", chain.len()),
+    );
+    if chain.len() == 1 && args.leaf_index.is_none() {
+        return Ok((chain, None));
+    }
+    let index = truststore::select_leaf(&chain, args.validation_host.as_deref(), args.leaf_index)
+        .map_err(|err| SimpleCheckResult::unknown(format!("{err:#}")).tag("cert_file"))?;
+    let count = chain.len();
+    let leaf = chain.remove(index);
+    let subject = X509Certificate::from_der(&leaf)
+        .map(|(_rem, cert)| cert.subject().to_string())
+        .unwrap_or_default();
+    chain.insert(0, leaf);
+    Ok((
+        chain,
+        Some(format!(
+            "Leaf: certificate at index {index} of {count} ({subject})"
+        )),
+    ))
+}
+
+enum Source<'a> {
+    Server(&'a str, u16),
+    File(&'a Path),
+}
+
+/// The results for the certificate and where the server redirects to, if asked.
+fn check_target(
+    args: &Args,
+    source: Source,
+    trust_store: &[Vec<u8>],
+) -> (Collection, Option<String>) {
+    let info = |text: &str| verbose(args.verbose, 1, "INFO: ", text);
+    let debug = |text: &str| verbose(args.verbose, 2, "DEBUG: ", text);
+
+    let chain_not_after = args.check_chain_expiry.then(|| {
+        parse_levels(
+            LevelsStrategy::Lower,
+            args.not_after.clone(),
+            Duration::seconds,
+        )
+    });
+    let not_after = parse_levels(
+        LevelsStrategy::Lower,
+        args.not_after.clone(),
+        Duration::seconds,
+    );
+    let cert_age = args
+        .cert_age
+        .clone()
+        .map(|lvl| parse_levels(LevelsStrategy::Upper, lvl, Duration::seconds));
+    let max_validity = args.max_validity.clone().map(|lvl| {
+        let days = |x: u32| Duration::days(x.into());
+        match lvl[..] {
+            // Warn only, like before the levels.
+            [warn] => LevelsChecker::warn_only(LevelsStrategy::Upper, days(warn)),
+            _ => parse_levels(LevelsStrategy::Upper, lvl, days),
+        }
+    });
+    let language = Language::from(args.language.clone());
+    let min_tls_version = args.min_tls_version.clone().map(TlsVersion::from);
+    let pubkey_bits = args
+        .pubkey_bits
+        .clone()
+        .map(|lvl| parse_levels(LevelsStrategy::Lower, lvl, |x: u32| x));
+    let sct_count = args
+        .sct_count
+        .clone()
+        .map(|lvl| parse_levels(LevelsStrategy::Lower, lvl, |x: u32| x));
+    let response_time = parse_levels(
+        LevelsStrategy::Upper,
+        args.response_time.clone(),
+        StdDuration::from_secs_f64,
+    );
+
+    let hostname = match source {
+        Source::Server(server, _port) => Some(server),
+        Source::File(_path) => None,
+    };
+    // Nothing was fetched from a file, so there is neither a response time,
+    // a protocol nor a staple to check.
+    let fetched = hostname.is_some();
+    let cert_checks = checks::CertChecks::builder()
+        .fetcher(Some(fetcher_config(args)))
+        .response(fetched.then(|| {
+            FetcherChecks::builder()
+                .response_time(Some(response_time))
+                .language(language)
+                .time_unit(args.time_unit.clone().map(TimeUnit::from))
+                .build()
+        }))
+        .protocol(fetched.then(|| {
+            ProtocolChecks::builder()
+                .min_version(min_tls_version)
+                .build()
+        }))
+        .verification(Some(
+            VerifChecks::builder()
+                .trust_store(trust_store)
+                .allow_self_signed(args.allow_self_signed)
+                .build(),
+        ))
+        .chain(Some(
+            ChainChecks::builder().not_after(chain_not_after).build(),
+        ))
+        .ocsp(fetched.then(|| {
+            OcspChecks::builder()
+                .missing_staple(args.ocsp_missing_staple.clone().into())
+                .build()
+        }))
+        .crl(match &args.crl_file {
+            Some(crl_file) => Some(Crl::File(crl_file.clone())),
+            None => args.check_crl.then_some(Crl::Fetch),
+        })
+        .certificate(Some(
+            CertChecks::builder()
+                .serial(args.serial.clone())
+                .expected_fingerprint(args.fingerprint.clone())
+                .subject_cn(args.subject_cn.clone())
+                .subject_alt_names(args.subject_alt_names.clone())
+                .required_sans(args.required_san.clone())
+                .missing_san(args.missing_san.clone().into())
+                .wildcard_min_labels(
+                    (args.wildcard_min_labels != 0).then_some(args.wildcard_min_labels),
+                )
+                .required_eku(args.required_eku.clone())
+                .missing_eku(args.missing_eku.clone().into())
+                .hostname(
+                    args.validation_host
+                        .clone()
+                        .or(hostname.filter(|_| args.verify_hostname).map(String::from)),
+                )
+                .sni(
+                    hostname
+                        .filter(|_| args.detect_default_cert)
+                        .map(|server| args.server_name.clone().unwrap_or(server.to_string())),
+                )
+                .subject_o(args.subject_o.clone())
+                .subject_ou(args.subject_ou.clone())
+                .issuer_cn(args.issuer_cn.clone())
+                .expected_issuer(args.issuer.clone())
+                .issuer_o(args.issuer_o.clone())
+                .issuer_ou(args.issuer_ou.clone())
+                .issuer_st(args.issuer_st.clone())
+                .issuer_c(args.issuer_c.clone())
+                .signature_algorithm(args.signature_algorithm.clone())
+                .forbidden_signature_algorithms(args.forbidden_signature_algorithms.clone())
+                .pubkey_algorithm(
+                    args.pubkey_algorithm
+                        .as_ref()
+                        .map(|sig| String::from(sig.as_str())),
+                )
+                .pubkey_size(args.pubkey_size)
+                .pubkey_bits(pubkey_bits)
+                .sct_count(sct_count)
+                .not_after(Some(not_after))
+                .warn_deadline(args.warn_deadline)
+                .crit_deadline(args.crit_deadline)
+                .not_before_skew(Duration::seconds(args.not_before_skew.into()))
+                .cert_age(cert_age)
+                .max_validity(max_validity)
+                .language(language)
+                .build(),
+        ))
+        .build();
+
+    let (mut collection, leaf, redirect) = match source {
+        Source::Server(server, port) => {
+            info("contact host...");
+            let response = match fetch(args, server, port) {
+                Ok(response) => response,
+                Err(err) => return (Collection::from(err), None),
+            };
+            debug(&format!(
+                "\n{}",
+                std::str::from_utf8(&to_pem(&response.chain[0])).expect("valid utf8")
+            ));
+            let mut collection = info::collect(
+                InfoConfig::builder()
+                    .server(server)
+                    .port(port)
+                    .addr(response.addr.map(|addr| addr.ip()))
+                    .server_name(args.server_name.as_deref())
+                    .validation_host(args.validation_host.as_deref())
+                    .unix_socket(args.unix_socket.as_deref())
+                    .build(),
+            );
+            info("check certificate...");
+            collection.join(&mut cert_checks.check(&response));
+            (collection, response.chain[0].clone(), response.redirect)
+        }
+        Source::File(path) => {
+            let (chain, leaf) = match load(args, path) {
+                Ok(loaded) => loaded,
+                Err(err) => return (Collection::from(err), None),
+            };
+            let mut collection = leaf.map_or_else(Collection::default, |leaf| {
+                Collection::from(SimpleCheckResult::ok(leaf).tag("leaf"))
+            });
+            info("check certificate...");
+            collection.join(&mut cert_checks.check_chain(&chain, None));
+            (collection, chain[0].clone(), None)
+        }
+    };
+    if let Some(path) = &args.baseline {
+        info("check certificate against baseline");
+        collection.join(&mut check_baseline(args, path, &leaf));
+    }
+    info("check certificate... done");
+    (collection, redirect)
+}
+
+/// Check the server, then the servers it redirects to over HTTPS, hop by hop.
+fn check_server(args: &Args, server: &str, port: u16, trust_store: &[Vec<u8>]) -> Collection {
+    let (mut collection, mut redirect) =
+        check_target(args, Source::Server(server, port), trust_store);
+    // The other servers are reached by their name, the overrides are for the first one.
+    let mut hop_args = args.clone();
+    hop_args.server_name = None;
+    hop_args.connect_addr = None;
+    hop_args.unix_socket = None;
+    hop_args.validation_host = None;
+    hop_args.baseline = None;
+    let mut visited = vec![(server.to_string(), port)];
+    for hop in 1..=args.follow_redirects {
+        let Some(location) = redirect.take() else {
+            break;
+        };
+        let target = if location.starts_with("https://") {
+            split_https_url(&location).map(|(host, port, _path)| (host, port))
+        } else if location.starts_with("http://") {
+            collection.join(&mut Collection::from(
+                SimpleCheckResult::warn(format!("Redirect {hop}: {location} leaves TLS"))
+                    .tag("redirect"),
+            ));
+            break;
+        } else {
+            // Relative, so on the same server.
+            Ok(visited[visited.len() - 1].clone())
+        };
+        let (host, hop_port) = match target {
+            Ok(target) => target,
+            Err(err) => {
+                collection.join(&mut Collection::from(
+                    SimpleCheckResult::warn(format!("Redirect {hop}: {err}")).tag("redirect"),
+                ));
+                break;
+            }
+        };
+        if visited.last() == Some(&(host.clone(), hop_port)) {
+            // Only `/` is requested, another path on the same server gives nothing new.
+            collection.join(&mut Collection::from(
+                SimpleCheckResult::notice(format!("Redirect {hop}: {location} on the same server"))
+                    .tag("redirect"),
+            ));
+            break;
+        }
+        if visited.contains(&(host.clone(), hop_port)) {
+            collection.join(&mut Collection::from(
+                SimpleCheckResult::warn(format!("Redirect {hop}: loop back to {location}"))
+                    .tag("redirect"),
+            ));
+            break;
+        }
+        collection.join(&mut Collection::from(
+            SimpleCheckResult::notice(format!("Redirect {hop}: {location}")).tag("redirect"),
+        ));
+        let (mut hop_collection, next) =
+            check_target(&hop_args, Source::Server(&host, hop_port), trust_store);
+        hop_collection.namespace(&format!("{host}:{hop_port}"));
+        collection.join(&mut hop_collection);
+        visited.push((host, hop_port));
+        redirect = next;
+    }
+    if let Some(location) = redirect {
+        collection.join(&mut Collection::from(
+            SimpleCheckResult::notice(format!(
+                "Redirect to {location} not followed, limit of {} hop(s) reached",
+                args.follow_redirects
+            ))
+            .tag("redirect"),
+        ));
+    }
+    collection
+}
+
+fn check_baseline(args: &Args, path: &Path, der: &[u8]) -> Collection {
+    let unknown = |text: String| Collection::from(SimpleCheckResult::unknown(text).tag("baseline"));
+    let current = match Snapshot::new(der) {
+        Ok(current) => current,
+        Err(err) => return unknown(format!("Baseline: {err}")),
+    };
+    match std::fs::read_to_string(path) {
+        Ok(text) => match Snapshot::parse(&text) {
+            Ok(saved) => baseline::check(
+                &current,
+                &saved,
+                BaselineChecks::builder()
+                    .renewal(args.baseline_renewal.clone().into())
+                    .build(),
+            ),
+            Err(err) => unknown(format!("Baseline {}: {err}", path.display())),
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            match std::fs::write(path, current.to_string()) {
+                Ok(()) => Collection::from(
+                    SimpleCheckResult::ok(format!("Baseline saved to {}", path.display()))
+                        .tag("baseline"),
+                ),
+                Err(err) => unknown(format!("Cannot write {}: {err}", path.display())),
+            }
+        }
+        Err(err) => unknown(format!("Cannot read {}: {err}", path.display())),
+    }
+}
+
+fn usage_error(err: &clap::Error) -> String {
+    // Keep the message, drop the usage and the tips after the first empty line.
+    let rendered = err.render().to_string();
+    let message = rendered
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "Invalid arguments: {}",
+        message.strip_prefix("error: ").unwrap_or(&message)
+    )
+}
+
+/// Report UNKNOWN before the monitoring kills a check that takes too long.
+fn start_watchdog(timeout: u64) {
+    std::thread::spawn(move || {
+        std::thread::sleep(StdDuration::from_secs(timeout));
+        check::bail_out(format!("Check exceeded overall timeout of {timeout}s"))
+    });
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // We ran into https://github.com/sfackler/rust-openssl/issues/575
     // without openssl_probe.
     openssl_probe::init_ssl_cert_env_vars();
+    check::bail_out_on_panic();
 
-    let args = Args::parse();
+    let args = Args::try_parse().unwrap_or_else(|err| match err.kind() {
+        ErrorKind::DisplayHelp
+        | ErrorKind::DisplayVersion
+        | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => err.exit(),
+        // Clap exits with 2, that is, CRIT, on usage errors.
+        _ => check::bail_out(usage_error(&err)),
+    });
+    if let Some(timeout) = args.overall_timeout {
+        start_watchdog(timeout);
+    }
 
     let info = |text: &str| verbose(args.verbose, 1, "INFO: ", text);
-    let debug = |text: &str| verbose(args.verbose, 2, "DEBUG: ", text);
 
     info("start check-cert");
 
-    let not_after = parse_levels(LevelsStrategy::Lower, args.not_after, Duration::seconds);
-    let response_time = parse_levels(
-        LevelsStrategy::Upper,
-        args.response_time,
-        StdDuration::from_secs_f64,
-    );
+    let url = args.url.as_deref().unwrap_or_default();
+
+    if args.dump_cert {
+        let chain = match &args.cert_file {
+            Some(path) => load(&args, path).map(|(chain, _leaf)| chain),
+            None => {
+                info("contact host...");
+                fetch(&args, url, args.port).map(|response| response.chain)
+            }
+        };
+        let chain = match chain {
+            Ok(chain) => chain,
+            Err(err) => {
+                let collection = Collection::from(err);
+                println!("{}", collection);
+                std::process::exit(check::exit_code(&collection))
+            }
+        };
+        match dump::dump(&chain[0]) {
+            Ok(json) => {
+                println!("{json}");
+                std::process::exit(0)
+            }
+            Err(err) => check::bail_out(format!("{:?}", err)),
+        }
+    }
 
     info("load trust store...");
-    let Ok(trust_store) = (match args.ca_store {
-        Some(ca_store) => truststore::load_store(&ca_store),
+    let Ok(trust_store) = (match &args.ca_store {
+        Some(ca_store) => truststore::load_store(ca_store),
         None => truststore::system(),
     }) else {
         check::abort("Failed to load trust store")
     };
     info(&format!("loaded {} certificates", trust_store.len()));
 
-    info("contact host...");
-    let start = Instant::now();
-    let chain = match fetcher::fetch_server_cert(
-        &args.url,
-        args.port,
-        FetcherConfig::builder()
-            .timeout((args.timeout != 0).then_some(StdDuration::new(args.timeout, 0)))
-            .build(),
-    ) {
-        Ok(chain) => chain,
-        Err(err) => check::abort(format!("{:?}", err)),
+    let mut collection = if let Some(path) = &args.cert_file {
+        check_target(&args, Source::File(path), &trust_store).0
+    } else if args.targets.is_empty() {
+        check_server(&args, url, args.port, &trust_store)
+    } else {
+        let targets = std::iter::once((url.to_string(), args.port))
+            .chain(args.targets.iter().cloned())
+            .collect::<Vec<_>>();
+        std::thread::scope(|scope| {
+            let handles = targets
+                .iter()
+                .map(|(server, port)| {
+                    let (args, trust_store) = (&args, &trust_store);
+                    scope.spawn(move || {
+                        let mut collection = check_server(args, server, *port, trust_store);
+                        collection.namespace(&format!("{server}:{port}"));
+                        collection
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .fold(Collection::default(), |mut out, handle| {
+                    out.join(&mut handle.join().expect("target check panicked"));
+                    out
+                })
+        })
     };
-    let elapsed = start.elapsed();
-    info(&format!(
-        "received chain of {} certificates from host",
-        chain.len()
-    ));
-
-    if chain.is_empty() {
-        check::abort("Empty or invalid certificate chain on host")
-    }
-
-    info("check certificate...");
-    debug(&format!(
-        "\n{}",
-        std::str::from_utf8(&to_pem(&chain[0])).expect("valid utf8")
-    ));
-    info(" 1/3 - check fetching process");
-    let mut collection = info::collect(
-        InfoConfig::builder()
-            .server(&args.url)
-            .port(args.port)
-            .build(),
-    );
-    collection.join(&mut fetcher_check::check(
-        elapsed,
-        FetcherChecks::builder()
-            .response_time(Some(response_time))
-            .build(),
-    ));
-    info(" 2/3 - verify certificate with trust store");
-    collection.join(&mut verification::check(
-        &chain,
-        VerifChecks::builder()
-            .trust_store(&trust_store)
-            .allow_self_signed(args.allow_self_signed)
-            .build(),
-    ));
-    info(" 3/3 - check certificate");
-    collection.join(&mut certificate::check(
-        &chain[0],
-        CertChecks::builder()
-            .serial(args.serial)
-            .subject_cn(args.subject_cn)
-            .subject_alt_names(args.subject_alt_names)
-            .subject_o(args.subject_o)
-            .subject_ou(args.subject_ou)
-            .issuer_cn(args.issuer_cn)
-            .issuer_o(args.issuer_o)
-            .issuer_ou(args.issuer_ou)
-            .issuer_st(args.issuer_st)
-            .issuer_c(args.issuer_c)
-            .signature_algorithm(args.signature_algorithm)
-            .pubkey_algorithm(args.pubkey_algorithm.map(|sig| String::from(sig.as_str())))
-            .pubkey_size(args.pubkey_size)
-            .not_after(Some(not_after))
-            .max_validity(args.max_validity.map(|x| Duration::days(x.into())))
-            .build(),
-    ));
-    info("check certificate... done");
 
+    if args.tags {
+        collection.show_tags();
+    }
+    if args.compact {
+        collection.compact();
+    }
     println!("{}", collection);
     std::process::exit(check::exit_code(&collection))
 }
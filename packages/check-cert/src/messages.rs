@@ -0,0 +1,138 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+//! Translated output of the checkers.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    En,
+    De,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    DnsResolve,
+    TcpConnect,
+    ProxyConnect,
+    StartTls,
+    TlsHandshake,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Seconds,
+    Milliseconds,
+}
+
+fn format_time(time: Duration, unit: TimeUnit) -> String {
+    match unit {
+        TimeUnit::Seconds => format!("{:.3} s", time.as_secs_f64()),
+        TimeUnit::Milliseconds => format!("{} ms", time.as_millis()),
+    }
+}
+
+fn phase_name(lang: Language, phase: Phase) -> &'static str {
+    match (lang, phase) {
+        (Language::En, Phase::DnsResolve) => "DNS resolution",
+        (Language::En, Phase::TcpConnect) => "TCP connect",
+        (Language::En, Phase::ProxyConnect) => "Proxy CONNECT",
+        (Language::En, Phase::StartTls) => "STARTTLS",
+        (Language::En, Phase::TlsHandshake) => "TLS handshake",
+        (Language::De, Phase::DnsResolve) => "DNS-Auflösung",
+        (Language::De, Phase::TcpConnect) => "TCP-Verbindungsaufbau",
+        (Language::De, Phase::ProxyConnect) => "Proxy-CONNECT",
+        (Language::De, Phase::StartTls) => "STARTTLS",
+        (Language::De, Phase::TlsHandshake) => "TLS-Handshake",
+    }
+}
+
+pub fn response_time(lang: Language, time: Duration, unit: TimeUnit) -> String {
+    match lang {
+        Language::En => format!("Response time: {}", format_time(time, unit)),
+        Language::De => format!("Antwortzeit: {}", format_time(time, unit)),
+    }
+}
+
+pub fn phase_time(lang: Language, phase: Phase, time: Duration, unit: TimeUnit) -> String {
+    format!("{}: {}", phase_name(lang, phase), format_time(time, unit))
+}
+
+pub fn succeeded_on_attempt(lang: Language, made: u8, allowed: u8) -> String {
+    match lang {
+        Language::En => format!("Connection succeeded on attempt {made}/{allowed}"),
+        Language::De => format!("Verbindung im {made}. von {allowed} Versuchen erfolgreich"),
+    }
+}
+
+pub fn expires_in(lang: Language, days: i64, date: impl Display) -> String {
+    match lang {
+        Language::En => format!("Certificate expires in {days} day(s) ({date})"),
+        Language::De => format!("Zertifikat läuft in {days} Tag(en) ab ({date})"),
+    }
+}
+
+pub fn expired_ago(lang: Language, days: i64, date: impl Display) -> String {
+    match lang {
+        Language::En => format!("Certificate expired {days} day(s) ago ({date})"),
+        Language::De => format!("Zertifikat seit {days} Tag(en) abgelaufen ({date})"),
+    }
+}
+
+pub fn subject_issuer(lang: Language, subject: impl Display, issuer: impl Display) -> String {
+    match lang {
+        Language::En => format!("Subject: {subject}\nIssuer: {issuer}"),
+        Language::De => format!("Inhaber: {subject}\nAussteller: {issuer}"),
+    }
+}
+
+#[cfg(test)]
+mod test_messages {
+    use super::{expires_in, phase_time, response_time, Language, Phase, TimeUnit};
+    use std::time::Duration;
+
+    #[test]
+    fn test_en() {
+        assert_eq!(
+            response_time(
+                Language::En,
+                Duration::from_millis(42),
+                TimeUnit::Milliseconds
+            ),
+            "Response time: 42 ms"
+        );
+        assert_eq!(
+            phase_time(
+                Language::En,
+                Phase::TlsHandshake,
+                Duration::from_millis(42),
+                TimeUnit::Seconds
+            ),
+            "TLS handshake: 0.042 s"
+        );
+        assert_eq!(
+            expires_in(Language::En, 3, "tomorrow"),
+            "Certificate expires in 3 day(s) (tomorrow)"
+        );
+    }
+
+    #[test]
+    fn test_de() {
+        assert_eq!(
+            response_time(
+                Language::De,
+                Duration::from_millis(42),
+                TimeUnit::Milliseconds
+            ),
+            "Antwortzeit: 42 ms"
+        );
+        assert_eq!(
+            expires_in(Language::De, 3, "morgen"),
+            "Zertifikat läuft in 3 Tag(en) ab (morgen)"
+        );
+    }
+}
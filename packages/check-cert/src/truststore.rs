@@ -2,10 +2,14 @@
 // This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
 // conditions defined in the file COPYING, which is part of this source code package.
 
-use anyhow::{anyhow, Result};
+use crate::checker::certificate::covers_hostname;
+use anyhow::{anyhow, bail, Context, Result};
+use openssl::pkcs7::Pkcs7;
 use openssl::x509::store::X509StoreBuilder;
 use openssl::x509::{X509Ref, X509};
 use std::path::Path;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
 
 pub fn system() -> Result<Vec<Vec<u8>>> {
     let mut store = X509StoreBuilder::new()?;
@@ -31,6 +35,140 @@ fn try_to_parse(content: &[u8]) -> Vec<Vec<u8>> {
     Vec::new()
 }
 
+/// Encoding of a certificate file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertFormat {
+    Pem,
+    Der,
+    /// PKCS#7 bundle (`.p7b`), PEM or DER encoded.
+    Pkcs7,
+}
+
+impl std::fmt::Display for CertFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pem => write!(f, "PEM"),
+            Self::Der => write!(f, "DER"),
+            Self::Pkcs7 => write!(f, "PKCS#7"),
+        }
+    }
+}
+
+fn trim_start(content: &[u8]) -> &[u8] {
+    let start = content
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(content.len());
+    &content[start..]
+}
+
+/// Guess the format from the PEM header or, for binary content,
+/// from whether it parses as a PKCS#7 bundle.
+pub fn sniff(content: &[u8]) -> Option<CertFormat> {
+    let content = trim_start(content);
+    if content.starts_with(b"-----BEGIN PKCS7") {
+        Some(CertFormat::Pkcs7)
+    } else if content.starts_with(b"-----BEGIN") {
+        Some(CertFormat::Pem)
+    } else if content.first() != Some(&0x30) {
+        // Both DER formats start with an ASN.1 SEQUENCE.
+        None
+    } else if Pkcs7::from_der(content).is_ok() {
+        Some(CertFormat::Pkcs7)
+    } else {
+        Some(CertFormat::Der)
+    }
+}
+
+fn parse(content: &[u8], format: CertFormat) -> Result<Vec<Vec<u8>>> {
+    Ok(match format {
+        CertFormat::Pem => X509::stack_from_pem(content)?
+            .iter()
+            .map(|cert| cert.to_der())
+            .collect::<Result<Vec<_>, _>>()?,
+        CertFormat::Der => {
+            X509::from_der(content)?;
+            vec![content.to_vec()]
+        }
+        CertFormat::Pkcs7 => {
+            let bundle = if trim_start(content).starts_with(b"-----BEGIN") {
+                Pkcs7::from_pem(content)?
+            } else {
+                Pkcs7::from_der(content)?
+            };
+            match bundle.signed().and_then(|signed| signed.certificates()) {
+                Some(certs) => certs
+                    .iter()
+                    .map(X509Ref::to_der)
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => Vec::new(),
+            }
+        }
+    })
+}
+
+/// Certificates of a single PEM, DER or PKCS#7 file, in the order of the file.
+/// The format is sniffed from the content unless given.
+pub fn load_certs(path: &Path, format: Option<CertFormat>) -> Result<Vec<Vec<u8>>> {
+    let content = std::fs::read(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let Some(format) = format.or_else(|| sniff(&content)) else {
+        bail!("No certificate in {}", path.display())
+    };
+    let certs = parse(&content, format)
+        .with_context(|| format!("Cannot parse {} as {format}", path.display()))?;
+    if certs.is_empty() {
+        bail!("No certificate in {}", path.display())
+    }
+    Ok(certs)
+}
+
+fn is_ca(cert: &X509Certificate) -> bool {
+    matches!(cert.basic_constraints(), Ok(Some(bc)) if bc.value.ca)
+}
+
+/// Index of the leaf among several certificates: the one at `index` if given,
+/// otherwise the first one for `hostname`, otherwise the first that is not a CA.
+pub fn select_leaf(
+    certs: &[Vec<u8>],
+    hostname: Option<&str>,
+    index: Option<usize>,
+) -> Result<usize> {
+    if let Some(index) = index {
+        if index >= certs.len() {
+            bail!(
+                "Leaf index {index} out of range, found {} certificate(s)",
+                certs.len()
+            )
+        }
+        return Ok(index);
+    }
+    let parsed = certs
+        .iter()
+        .enumerate()
+        .map(|(index, der)| match X509Certificate::from_der(der) {
+            Ok((_rem, cert)) => Ok(cert),
+            Err(_) => Err(anyhow!("Failed to parse certificate at index {index}")),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    match hostname {
+        Some(hostname) => parsed
+            .iter()
+            .position(|cert| covers_hostname(cert, hostname))
+            .ok_or_else(|| {
+                anyhow!(
+                    "No certificate for {hostname} among {} certificate(s)",
+                    certs.len()
+                )
+            }),
+        None => parsed.iter().position(|cert| !is_ca(cert)).ok_or_else(|| {
+            anyhow!(
+                "No end-entity certificate among {} certificate(s)",
+                certs.len()
+            )
+        }),
+    }
+}
+
 pub fn load_store(path: &impl AsRef<Path>) -> Result<Vec<Vec<u8>>> {
     let path = path.as_ref();
 
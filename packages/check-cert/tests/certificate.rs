@@ -3,8 +3,8 @@
 // conditions defined in the file COPYING, which is part of this source code package.
 
 use assertor::*;
-use check_cert::check;
-use check_cert::checker::certificate::{self, Config as CertConfig};
+use check_cert::check::{self, Levels, LevelsChecker, LevelsStrategy};
+use check_cert::checker::certificate::{self, Config as CertConfig, MissingEku, MissingSan};
 
 fn as_der(crt: &[u8]) -> Vec<u8> {
     openssl::x509::X509::from_pem(crt)
@@ -103,3 +103,432 @@ fn test_signature_algorithm_ee_pss_sha1_wrong_alg() {
         )
     );
 }
+
+#[test]
+fn test_forbidden_signature_algorithm_sha256_with_rsa_encryption() {
+    static DER: &[u8] = include_bytes!("../assets/cert.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .forbidden_signature_algorithms(Some(
+                certificate::WEAK_SIGNATURE_ALGORITHMS
+                    .map(String::from)
+                    .to_vec(),
+            ))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 0);
+    assert_that!(coll
+        .to_string()
+        .contains("Signature algorithm: sha256WithRSAEncryption"));
+}
+
+#[test]
+fn test_forbidden_signature_algorithm_by_name() {
+    static DER: &[u8] = include_bytes!("../assets/cert.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .forbidden_signature_algorithms(Some(vec![String::from("SHA256WithRSAEncryption")]))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_that!(coll
+        .to_string()
+        .contains("Weak signature algorithm: sha256WithRSAEncryption (!!)"));
+}
+
+#[test]
+fn test_hostname() {
+    static DER: &[u8] = include_bytes!("../assets/certificate.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .hostname(Some(String::from("lists.for-our.info")))
+            .build(),
+    );
+    assert_that!(coll
+        .to_string()
+        .contains("\nHostname lists.for-our.info matches lists.for-our.info"));
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .hostname(Some(String::from("www.for-our.info")))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_that!(coll
+        .to_string()
+        .contains("Hostname www.for-our.info not in SAN list (!!)"));
+}
+
+#[test]
+fn test_hostname_ip_address() {
+    static DER: &[u8] = include_bytes!("../assets/ip-san.der");
+
+    let check = |hostname: &str| {
+        certificate::check(
+            DER,
+            CertConfig::builder()
+                .hostname(Some(String::from(hostname)))
+                .build(),
+        )
+    };
+    for (hostname, text) in [
+        ("192.0.2.1", "\nHostname 192.0.2.1 matches 192.0.2.1"),
+        ("2001:db8::1", "\nHostname 2001:db8::1 matches 2001:db8::1"),
+        (
+            "[2001:db8::1]",
+            "\nHostname [2001:db8::1] matches 2001:db8::1",
+        ),
+        (
+            "ip.example.com",
+            "\nHostname ip.example.com matches ip.example.com",
+        ),
+    ] {
+        let coll = check(hostname);
+        assert_eq!(check::exit_code(&coll), 0, "{coll}");
+        assert_that!(coll.to_string().contains(text));
+    }
+    let coll = check("192.0.2.2");
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_that!(coll
+        .to_string()
+        .contains("Hostname 192.0.2.2 not in SAN list (!!)"));
+}
+
+#[test]
+fn test_fingerprint() {
+    static DER: &[u8] = include_bytes!("../assets/certificate.der");
+    static FINGERPRINT: &str =
+        "8F:A4:CB:4E:93:8B:5A:85:5F:B1:49:7F:A2:88:AC:55:72:3E:01:09:D0:51:8B:50:54:81:34:8D:EC:0D:E4:71";
+
+    for expected in [
+        String::from(FINGERPRINT),
+        FINGERPRINT.to_lowercase(),
+        FINGERPRINT.replace(':', ""),
+    ] {
+        let coll = certificate::check(
+            DER,
+            CertConfig::builder()
+                .expected_fingerprint(Some(expected))
+                .build(),
+        );
+        assert_that!(coll
+            .to_string()
+            .contains(&format!("\nFingerprint (SHA-256): {FINGERPRINT}")));
+    }
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .expected_fingerprint(Some(String::from("00:11:22")))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_that!(coll.to_string().contains(&format!(
+        "Fingerprint mismatch: {FINGERPRINT} but expected 00:11:22 (!!)"
+    )));
+}
+
+#[test]
+fn test_extended_key_usage() {
+    static DER: &[u8] = include_bytes!("../assets/certificate.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .required_eku(Some(vec![String::from("serverAuth")]))
+            .build(),
+    );
+    assert_that!(coll
+        .to_string()
+        .contains(", Extended key usage: serverAuth, clientAuth, "));
+}
+
+#[test]
+fn test_extended_key_usage_by_oid() {
+    static DER: &[u8] = include_bytes!("../assets/certificate.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .required_eku(Some(vec![String::from("1.3.6.1.5.5.7.3.1")]))
+            .build(),
+    );
+    assert_that!(coll
+        .to_string()
+        .contains(", Extended key usage: serverAuth, clientAuth, "));
+
+    let coll = certificate::check(
+        include_bytes!("../assets/eku-cert-sign.der"),
+        CertConfig::builder()
+            .required_eku(Some(vec![String::from("1.3.6.1.5.5.7.3.1")]))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 1);
+    assert_that!(coll.to_string().contains("does not allow serverAuth (!)"));
+}
+
+#[test]
+fn test_extended_key_usage_missing_server_auth() {
+    static DER: &[u8] = include_bytes!("../assets/eku-client-auth.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .required_eku(Some(vec![String::from("serverAuth")]))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 1);
+    assert_that!(coll
+        .to_string()
+        .contains("Extended key usage: clientAuth but expected serverAuth (!)"));
+}
+
+#[test]
+fn test_required_sans() {
+    static DER: &[u8] = include_bytes!("../assets/wildcard.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .required_sans(Some(vec![
+                String::from("example.com"),
+                String::from("www.example.com"),
+            ]))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 0);
+    assert_that!(coll
+        .to_string()
+        .contains("\nRequired subject alternative names: example.com, www.example.com\n"));
+}
+
+#[test]
+fn test_required_sans_missing() {
+    static DER: &[u8] = include_bytes!("../assets/wildcard.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .required_sans(Some(vec![
+                String::from("www.example.com"),
+                String::from("a.b.example.com"),
+                String::from("example.org"),
+            ]))
+            .missing_san(MissingSan::Crit)
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_that!(coll.to_string().contains(
+        r#"Required subject alternative names: missing "a.b.example.com", "example.org" (!!)"#
+    ));
+}
+
+#[test]
+fn test_wildcard_labels() {
+    static DER: &[u8] = include_bytes!("../assets/wildcard.der");
+
+    let coll = certificate::check(DER, CertConfig::builder().build());
+    assert_eq!(check::exit_code(&coll), 0);
+    assert_that!(coll.to_string().contains("Wildcard")).is_false();
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder().wildcard_min_labels(Some(3)).build(),
+    );
+    assert_eq!(check::exit_code(&coll), 1);
+    assert_that!(coll.to_string().contains(
+        r#"Wildcard subject alternative names too broad: "*.example.com" (expected at least 3 label(s) after the wildcard) (!)"#
+    ));
+}
+
+#[test]
+fn test_broad_wildcards() {
+    static DER: &[u8] = include_bytes!("../assets/broad-wildcard.der");
+
+    let coll = certificate::check(DER, CertConfig::builder().build());
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_that!(coll.to_string().contains(
+        r#"Wildcard subject alternative names too broad: "*.com", "*" (expected at least 2 label(s) after the wildcard) (!!)"#
+    ));
+}
+
+#[test]
+fn test_default_cert() {
+    static DER: &[u8] = include_bytes!("../assets/wildcard.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .sni(Some(String::from("www.example.com")))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 0);
+    assert_that!(coll
+        .to_string()
+        .contains("\nCertificate matches SNI name www.example.com\n"));
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .sni(Some(String::from("www.example.org")))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 1);
+    assert_that!(coll.to_string().contains(
+        "Certificate for CN=example.com (SAN: example.com, *.example.com) \
+        does not match SNI name www.example.org, likely the default certificate of the server (!)"
+    ));
+}
+
+#[test]
+fn test_key_usage_not_for_server_auth() {
+    static DER: &[u8] = include_bytes!("../assets/eku-cert-sign.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .required_eku(Some(vec![String::from("serverAuth")]))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 1);
+    assert_that!(coll.to_string().contains(
+        "Extended key usage: serverAuth but key usage Key Cert Sign does not allow serverAuth (!)"
+    ));
+}
+
+#[test]
+fn test_no_extended_key_usage() {
+    static DER: &[u8] = include_bytes!("../assets/IGC_A.der");
+
+    for (missing_eku, text) in [
+        (MissingEku::Any, "No extended key usage (any)"),
+        (MissingEku::Warn, "No extended key usage (any) (!)"),
+        (MissingEku::Crit, "No extended key usage (any) (!!)"),
+    ] {
+        let coll = certificate::check(
+            DER,
+            CertConfig::builder()
+                .required_eku(Some(vec![String::from("serverAuth")]))
+                .missing_eku(missing_eku)
+                .build(),
+        );
+        assert_that!(coll.to_string().contains(text));
+    }
+}
+
+#[test]
+fn test_sct_count() {
+    static DER: &[u8] = include_bytes!("../assets/certificate.der");
+
+    let levels =
+        |warn, crit| LevelsChecker::try_new(LevelsStrategy::Lower, Levels { warn, crit }).unwrap();
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder().sct_count(Some(levels(2, 1))).build(),
+    );
+    let out = coll.to_string();
+    assert_that!(out.contains(" sct_count=2;2;1;; "));
+    assert_that!(out.contains("\nSigned certificate timestamps: 2\nSCT from log 29:3C:51:96:"));
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder().sct_count(Some(levels(4, 3))).build(),
+    );
+    assert_that!(coll
+        .to_string()
+        .contains(", Signed certificate timestamps: 2 (below crit level) (!!), "));
+}
+
+#[test]
+fn test_expected_issuer() {
+    static DER: &[u8] = include_bytes!("../assets/certificate.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .expected_issuer(Some(String::from(
+                "CN=Let's Encrypt Authority X3,O=Let's Encrypt,C=US",
+            )))
+            .build(),
+    );
+    assert_that!(coll
+        .to_string()
+        .contains("\nIssuer DN: C=US, O=Let's Encrypt, CN=Let's Encrypt Authority X3\n"));
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .expected_issuer(Some(String::from("C=US, O=Internal, CN=Internal CA")))
+            .build(),
+    );
+    assert_that!(coll.to_string().contains(
+        "Issuer DN: C=US, O=Let's Encrypt, CN=Let's Encrypt Authority X3 \
+        but expected C=US, O=Internal, CN=Internal CA (!)"
+    ));
+}
+
+#[test]
+fn test_pubkey_bits_by_key_type() {
+    let levels = || {
+        LevelsChecker::try_new(
+            LevelsStrategy::Lower,
+            Levels {
+                warn: 3072,
+                crit: 2048,
+            },
+        )
+        .unwrap()
+    };
+    for (der, text) in [
+        (
+            &include_bytes!("../assets/certificate.der")[..],
+            "Public key size: 2048 bits (RSA) (below warn level) (!)",
+        ),
+        (
+            &include_bytes!("../assets/eku-client-auth.der")[..],
+            "\nPublic key size: EC P-256 (128 security bits, comparable to 3072 bits RSA)",
+        ),
+        (
+            &include_bytes!("../assets/ed25519.der")[..],
+            "\nPublic key size: Ed25519 (128 security bits, comparable to 3072 bits RSA)",
+        ),
+    ] {
+        let coll = certificate::check(
+            der,
+            CertConfig::builder().pubkey_bits(Some(levels())).build(),
+        );
+        assert!(coll.to_string().contains(text), "{coll}");
+    }
+}
+
+#[test]
+fn test_pubkey_bits_ed25519() {
+    static DER: &[u8] = include_bytes!("../assets/ed25519.der");
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .pubkey_bits(Some(
+                LevelsChecker::try_new(
+                    LevelsStrategy::Lower,
+                    Levels {
+                        warn: 2048,
+                        crit: 1024,
+                    },
+                )
+                .unwrap(),
+            ))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 0);
+    assert_that!(coll
+        .to_string()
+        .contains("\nPublic key size: Ed25519 (128 security bits, comparable to 3072 bits RSA)"));
+}
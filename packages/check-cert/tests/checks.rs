@@ -0,0 +1,168 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+use check_cert::check::{self, Levels, LevelsChecker, LevelsStrategy};
+use check_cert::checker::certificate::Config as CertConfig;
+use check_cert::checker::chain::Config as ChainConfig;
+use check_cert::checker::fetcher::Config as ResponseConfig;
+use check_cert::checker::ocsp::{Config as OcspConfig, MissingStaple};
+use check_cert::checker::protocol::Config as ProtocolConfig;
+use check_cert::checker::verification::Config as VerifConfig;
+use check_cert::checks::{CertChecks, Crl};
+use check_cert::fetcher::Config as FetcherConfig;
+use openssl::pkey::PKey;
+use openssl::ssl::{SslAcceptor, SslMethod};
+use openssl::x509::X509;
+use std::io::Read;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
+
+static LEAF: &[u8] = include_bytes!("../assets/ocsp-leaf.der");
+static LEAF_KEY: &[u8] = include_bytes!("../assets/ocsp-leaf.key");
+static CA: &[u8] = include_bytes!("../assets/ocsp-ca.der");
+static OCSP_GOOD: &[u8] = include_bytes!("../assets/ocsp-response-good.der");
+
+/// Serve the leaf and its CA with a good OCSP staple for one handshake.
+fn serve() -> u16 {
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate(&X509::from_der(LEAF).unwrap())
+        .unwrap();
+    acceptor
+        .set_private_key(&PKey::private_key_from_pem(LEAF_KEY).unwrap())
+        .unwrap();
+    acceptor
+        .add_extra_chain_cert(X509::from_der(CA).unwrap())
+        .unwrap();
+    acceptor
+        .set_status_callback(|ssl| {
+            ssl.set_ocsp_status(OCSP_GOOD)?;
+            Ok(true)
+        })
+        .unwrap();
+    let acceptor = acceptor.build();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        if let Ok(mut stream) = acceptor.accept(stream) {
+            // Until the client is done.
+            let _ = stream.read(&mut [0; 1]);
+        }
+    });
+    port
+}
+
+#[test]
+fn test_fetch_failure_skips_checks() {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let coll = CertChecks::builder()
+        .fetcher(Some(
+            FetcherConfig::builder()
+                .timeout(Some(Duration::from_millis(200)))
+                .build(),
+        ))
+        .chain(Some(ChainConfig::builder().build()))
+        .certificate(Some(CertConfig::builder().build()))
+        .build()
+        .run("127.0.0.1", port);
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_eq!(coll.to_string(), "Connection refused (!!)");
+}
+
+fn all_checks(trust_store: &[Vec<u8>]) -> CertChecks {
+    CertChecks::builder()
+        .fetcher(Some(
+            FetcherConfig::builder()
+                .timeout(Some(Duration::from_secs(5)))
+                .server_name(Some(String::from("leaf.example.com")))
+                .build(),
+        ))
+        .response(Some(ResponseConfig::builder().build()))
+        .protocol(Some(ProtocolConfig::builder().build()))
+        .verification(Some(
+            VerifConfig::builder()
+                .trust_store(trust_store)
+                .allow_self_signed(false)
+                .build(),
+        ))
+        .chain(Some(
+            ChainConfig::builder()
+                .not_after(Some(
+                    LevelsChecker::try_new(
+                        LevelsStrategy::Lower,
+                        Levels {
+                            warn: time::Duration::days(30),
+                            crit: time::Duration::ZERO,
+                        },
+                    )
+                    .unwrap(),
+                ))
+                .build(),
+        ))
+        .ocsp(Some(
+            OcspConfig::builder()
+                .missing_staple(MissingStaple::Crit)
+                .build(),
+        ))
+        .crl(Some(Crl::File(
+            [env!("CARGO_MANIFEST_DIR"), "assets", "crl.der"]
+                .iter()
+                .collect::<PathBuf>(),
+        )))
+        .certificate(Some(
+            CertConfig::builder()
+                .hostname(Some(String::from("leaf.example.com")))
+                .build(),
+        ))
+        .build()
+}
+
+#[test]
+fn test_all_checks_ok() {
+    let port = serve();
+    let trust_store = [CA.to_vec()];
+    let coll = all_checks(&trust_store).run("127.0.0.1", port);
+    assert_eq!(check::exit_code(&coll), 0, "{coll}");
+    let output = coll.to_string();
+    assert!(output.contains("OCSP: good"), "{output}");
+    assert!(output.contains("CRL: not revoked"), "{output}");
+}
+
+#[test]
+fn test_tags() {
+    let port = serve();
+    let trust_store = [CA.to_vec()];
+    let mut coll = all_checks(&trust_store).run("127.0.0.1", port);
+    coll.show_tags();
+    let output = coll.to_string();
+    for tag in [
+        "response_time",
+        "protocol",
+        "cipher",
+        "verification",
+        "chain_expiry",
+        "ocsp",
+        "crl",
+        "hostname",
+        "expiry",
+        "lifetime",
+    ] {
+        assert!(output.contains(&format!("[{tag}] ")), "{tag}: {output}");
+    }
+
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut coll = all_checks(&trust_store).run("127.0.0.1", port);
+    coll.show_tags();
+    assert_eq!(coll.to_string(), "[connection] Connection refused (!!)");
+}
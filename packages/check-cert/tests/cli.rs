@@ -0,0 +1,128 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+use assert_cmd::Command;
+
+#[test]
+fn test_usage_error_is_unknown() {
+    let output = Command::cargo_bin("check_cert")
+        .unwrap()
+        .arg("--bogus")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Invalid arguments: unexpected argument '--bogus' found (?)\n"
+    );
+}
+
+#[test]
+fn test_single_host_options_conflict_with_targets() {
+    for option in ["--server-name", "--validation-host", "--baseline"] {
+        let output = Command::cargo_bin("check_cert")
+            .unwrap()
+            .args(["--url", "example.com", "--target", "example.org"])
+            .args([option, "example.net"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3), "{option}");
+        assert!(String::from_utf8(output.stdout)
+            .unwrap()
+            .starts_with("Invalid arguments: the argument"));
+    }
+}
+
+#[test]
+fn test_unreadable_cert_file_is_one_line() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("missing.pem");
+    let output = Command::cargo_bin("check_cert")
+        .unwrap()
+        .env("RUST_BACKTRACE", "1")
+        .arg("--cert-file")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "Cannot read {}: No such file or directory (os error 2) (?)\n",
+            path.display()
+        )
+    );
+}
+
+#[test]
+fn test_bad_leaf_index_is_one_line() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("chain.p7b");
+    std::fs::write(&path, include_bytes!("../assets/ocsp-chain.p7b")).unwrap();
+    let output = Command::cargo_bin("check_cert")
+        .unwrap()
+        .env("RUST_BACKTRACE", "1")
+        .arg("--cert-file")
+        .arg(&path)
+        .args(["--leaf-index", "5"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Leaf index 5 out of range, found 2 certificate(s) (?)\n"
+    );
+}
+
+#[test]
+fn test_help_is_ok() {
+    Command::cargo_bin("check_cert")
+        .unwrap()
+        .arg("--help")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_overall_timeout() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+        .to_string();
+    // The retries outlast the overall timeout.
+    let output = Command::cargo_bin("check_cert")
+        .unwrap()
+        .args(["--url", "127.0.0.1", "--port", &port])
+        .args([
+            "--retries",
+            "3",
+            "--retry-delay",
+            "10",
+            "--overall-timeout",
+            "1",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Check exceeded overall timeout of 1s (?)\n"
+    );
+}
+
+#[test]
+fn test_hanging_server_is_crit() {
+    // Accepts through the backlog but never answers the handshake.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port().to_string();
+    let output = Command::cargo_bin("check_cert")
+        .unwrap()
+        .args(["--url", "127.0.0.1", "--port", &port, "--timeout", "1"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    drop(listener);
+}
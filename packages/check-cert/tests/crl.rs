@@ -0,0 +1,80 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+use anyhow::anyhow;
+use assertor::*;
+use check_cert::check;
+use check_cert::checker::crl;
+
+static CA: &[u8] = include_bytes!("../assets/ocsp-ca.der");
+static LEAF: &[u8] = include_bytes!("../assets/ocsp-leaf.der");
+static REVOKED_LEAF: &[u8] = include_bytes!("../assets/ocsp-revoked-leaf.der");
+static CDP_LEAF: &[u8] = include_bytes!("../assets/crl-leaf.der");
+static CRL: &[u8] = include_bytes!("../assets/crl.der");
+static CRL_PEM: &[u8] = include_bytes!("../assets/crl.pem");
+static EXPIRED_CRL: &[u8] = include_bytes!("../assets/crl-expired.der");
+static FORGED_CRL: &[u8] = include_bytes!("../assets/crl-forged.der");
+
+#[test]
+fn test_distribution_points() {
+    assert_eq!(
+        crl::distribution_points(CDP_LEAF),
+        vec![String::from("http://crl.example.com/ca.crl")]
+    );
+    assert!(crl::distribution_points(LEAF).is_empty());
+}
+
+#[test]
+fn test_not_revoked() {
+    for crl in [CRL, CRL_PEM] {
+        let coll = crl::check(Ok(crl.to_vec()), &[LEAF.to_vec(), CA.to_vec()]);
+        assert_eq!(check::exit_code(&coll), 0);
+        assert_that!(coll
+            .to_string()
+            .starts_with("CRL: not revoked (next update: Sep 20"));
+    }
+}
+
+#[test]
+fn test_revoked() {
+    let coll = crl::check(Ok(CRL.to_vec()), &[REVOKED_LEAF.to_vec(), CA.to_vec()]);
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_eq!(
+        coll.to_string(),
+        "CRL: revoked on Jan  1 00:00:00 2024 GMT (!!)"
+    );
+}
+
+#[test]
+fn test_outdated() {
+    let coll = crl::check(Ok(EXPIRED_CRL.to_vec()), &[LEAF.to_vec(), CA.to_vec()]);
+    assert_eq!(check::exit_code(&coll), 1);
+    assert_that!(coll.to_string().starts_with("CRL: outdated (next update: "));
+}
+
+#[test]
+fn test_forged() {
+    let coll = crl::check(Ok(FORGED_CRL.to_vec()), &[LEAF.to_vec(), CA.to_vec()]);
+    assert_eq!(check::exit_code(&coll), 1);
+    assert_eq!(
+        coll.to_string(),
+        "CRL: revocation list not signed by the issuer (!)"
+    );
+}
+
+#[test]
+fn test_unreachable() {
+    let coll = crl::check(
+        Err(anyhow!("Connection refused")),
+        &[LEAF.to_vec(), CA.to_vec()],
+    );
+    assert_eq!(check::exit_code(&coll), 1);
+    assert_eq!(coll.to_string(), "CRL: Connection refused (!)");
+}
+
+#[test]
+fn test_issuer_missing() {
+    let coll = crl::check(Ok(CRL.to_vec()), &[LEAF.to_vec()]);
+    assert_eq!(check::exit_code(&coll), 3);
+}
@@ -0,0 +1,31 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+use check_cert::dump;
+
+#[test]
+fn test_dump_cert() {
+    static DER: &[u8] = include_bytes!("../assets/certificate.der");
+
+    assert_eq!(
+        dump::dump(DER).unwrap(),
+        r#"{
+  "subject": "CN=lists.for-our.info",
+  "issuer": "C=US, O=Let's Encrypt, CN=Let's Encrypt Authority X3",
+  "serial": "03:20:48:03:0b:bb:34:10:f9:09:3c:57:f2:cb:83:08:c8:05",
+  "not_before": "2019-07-12T11:12:30Z",
+  "not_after": "2019-10-10T11:12:30Z",
+  "subject_alt_names": ["lists.for-our.info"],
+  "signature_algorithm": "sha256WithRSAEncryption",
+  "public_key_algorithm": "RSA",
+  "public_key_size": 2048,
+  "sha256_fingerprint": "8F:A4:CB:4E:93:8B:5A:85:5F:B1:49:7F:A2:88:AC:55:72:3E:01:09:D0:51:8B:50:54:81:34:8D:EC:0D:E4:71"
+}"#
+    );
+}
+
+#[test]
+fn test_dump_invalid_cert() {
+    assert!(dump::dump(b"not a certificate").is_err());
+}
@@ -1,4 +1,4 @@
-use check_cert::check;
+use check_cert::check::{self, Levels, LevelsChecker, LevelsStrategy};
 use check_cert::checker::certificate::{self, Config as CertConfig};
 
 // Taken from `x509-parser`.
@@ -7,11 +7,41 @@ static DER: &[u8] = include_bytes!("../assets/IGC_A.der");
 static SERIAL: &str = "39:11:45:10:94";
 static PUBKEY_ALG: &str = "RSA";
 static PUBKEY_SZ: usize = 2048;
+static DN: &str =
+    "C=FR, ST=France, L=Paris, O=PM/SGDN, OU=DCSSI, CN=IGC/A, Email=igca@sgdn.pm.gouv.fr";
 
 fn s(s: &str) -> Option<String> {
     Some(String::from(s))
 }
 
+// The certificate has expired, the remaining validity and the age change every second.
+fn without_validity(output: String) -> String {
+    let mut lines = output.lines();
+    let first = lines.next().unwrap_or_default();
+    let first = match first.split_once(" | ") {
+        None => first.to_string(),
+        Some((summary, metrics)) => {
+            let metrics = metrics
+                .split(' ')
+                .filter(|m| {
+                    !m.starts_with("certificate_remaining_validity=") && !m.starts_with("cert_age=")
+                })
+                .collect::<Vec<_>>();
+            if metrics.is_empty() {
+                summary.to_string()
+            } else {
+                format!("{summary} | {}", metrics.join(" "))
+            }
+        }
+    };
+    std::iter::once(first.as_str())
+        .chain(lines.filter(|l| {
+            !l.starts_with("Certificate expired ") && !l.starts_with("Certificate age: ")
+        }))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 #[test]
 fn test_cert_ok() {
     let coll = certificate::check(
@@ -32,10 +62,14 @@ fn test_cert_ok() {
             .build(),
     );
     assert_eq!(check::exit_code(&coll), 0);
+    assert!(coll
+        .to_string()
+        .starts_with("Subject CN: IGC/A | certificate_remaining_validity=-"));
+    assert!(coll.to_string().contains("\nCertificate expired "));
     assert_eq!(
-        coll.to_string(),
+        without_validity(coll.to_string()),
         format!(
-            "Subject CN: IGC/A\n\
+            "Subject CN: IGC/A | cert_lifetime=563155199s;;;;\n\
             Subject CN: IGC/A\n\
             Subject O: PM/SGDN\n\
             Subject OU: DCSSI\n\
@@ -47,7 +81,10 @@ fn test_cert_ok() {
             Issuer C: FR\n\
             Certificate signature algorithm: sha1WithRSAEncryption\n\
             Public key algorithm: {PUBKEY_ALG}\n\
-            Public key size: {PUBKEY_SZ}"
+            Public key size: {PUBKEY_SZ}\n\
+            Subject: {DN}\n\
+            Issuer: {DN}\n\
+            Certificate lifetime: 6517 day(s)"
         )
     );
 }
@@ -71,10 +108,11 @@ fn test_cert_wrong_serial() {
     );
     assert_eq!(check::exit_code(&coll), 1);
     assert_eq!(
-        coll.to_string(),
+        without_validity(coll.to_string()),
         format!(
             "Subject CN: IGC/A, \
-            Serial number: {SERIAL} but expected {serial} (!)\n\
+            Serial number: {SERIAL} but expected {serial} (!) \
+            | cert_lifetime=563155199s;;;;\n\
             Subject CN: IGC/A\n\
             Subject O: PM/SGDN\n\
             Subject OU: DCSSI\n\
@@ -82,7 +120,114 @@ fn test_cert_wrong_serial() {
             Issuer O: PM/SGDN\n\
             Issuer OU: DCSSI\n\
             Issuer ST: France\n\
-            Issuer C: FR"
+            Issuer C: FR\n\
+            Subject: {DN}\n\
+            Issuer: {DN}\n\
+            Certificate lifetime: 6517 day(s)"
+        )
+    );
+}
+
+#[test]
+fn test_cert_weak_signature_algorithm() {
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .forbidden_signature_algorithms(Some(
+                certificate::WEAK_SIGNATURE_ALGORITHMS
+                    .map(String::from)
+                    .to_vec(),
+            ))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_eq!(
+        without_validity(coll.to_string()),
+        format!(
+            "Subject CN: IGC/A, Weak signature algorithm: sha1WithRSAEncryption (!!) \
+            | cert_lifetime=563155199s;;;;\n\
+            Subject CN: IGC/A\n\
+            Issuer CN: IGC/A\n\
+            Subject: {DN}\n\
+            Issuer: {DN}\n\
+            Certificate lifetime: 6517 day(s)"
         )
     );
 }
+
+#[test]
+fn test_cert_pubkey_bits() {
+    let levels =
+        |warn, crit| LevelsChecker::try_new(LevelsStrategy::Lower, Levels { warn, crit }).unwrap();
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .pubkey_bits(Some(levels(2048, 1024)))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 0);
+    assert_eq!(
+        without_validity(coll.to_string()),
+        format!(
+            "Subject CN: IGC/A | pubkey_bits=2048bits;2048;1024;; cert_lifetime=563155199s;;;;\n\
+            Subject CN: IGC/A\n\
+            Issuer CN: IGC/A\n\
+            Public key size: 2048 bits (RSA)\n\
+            Subject: {DN}\n\
+            Issuer: {DN}\n\
+            Certificate lifetime: 6517 day(s)"
+        )
+    );
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .pubkey_bits(Some(levels(3072, 2049)))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 2);
+    assert!(coll.to_string().starts_with(
+        "Subject CN: IGC/A, Public key size: 2048 bits (RSA) (below crit level) (!!)"
+    ));
+}
+
+#[test]
+fn test_cert_max_validity() {
+    let days = time::Duration::days;
+    let levels =
+        |warn, crit| LevelsChecker::try_new(LevelsStrategy::Upper, Levels { warn, crit }).unwrap();
+
+    let warn_only = |warn| LevelsChecker::warn_only(LevelsStrategy::Upper, warn);
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .max_validity(Some(warn_only(days(7000))))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 0);
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .max_validity(Some(warn_only(days(3650))))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 1);
+    assert!(without_validity(coll.to_string()).contains(
+        "Certificate lifetime: 6517 day(s) (above warn level) (!) \
+        | cert_lifetime=563155199s;315360000;;;"
+    ));
+
+    let coll = certificate::check(
+        DER,
+        CertConfig::builder()
+            .max_validity(Some(levels(days(3650), days(7300))))
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 1);
+    assert!(without_validity(coll.to_string()).starts_with(
+        "Subject CN: IGC/A, Certificate lifetime: 6517 day(s) (above warn level) (!) \
+        | cert_lifetime=563155199s;315360000;630720000;;"
+    ));
+}
@@ -0,0 +1,88 @@
+// Copyright (C) 2023 Checkmk GmbH - License: GNU General Public License v2
+// This file is part of Checkmk (https://checkmk.com). It is subject to the terms and
+// conditions defined in the file COPYING, which is part of this source code package.
+
+use assertor::*;
+use check_cert::check;
+use check_cert::checker::ocsp::{self, Config, MissingStaple};
+
+static CA: &[u8] = include_bytes!("../assets/ocsp-ca.der");
+static LEAF: &[u8] = include_bytes!("../assets/ocsp-leaf.der");
+static REVOKED_LEAF: &[u8] = include_bytes!("../assets/ocsp-revoked-leaf.der");
+static GOOD: &[u8] = include_bytes!("../assets/ocsp-response-good.der");
+static REVOKED: &[u8] = include_bytes!("../assets/ocsp-response-revoked.der");
+// "good" for LEAF but signed by an unrelated self-signed "OCSP Test CA".
+static FORGED: &[u8] = include_bytes!("../assets/ocsp-response-forged.der");
+
+#[test]
+fn test_good() {
+    let coll = ocsp::check(
+        Some(GOOD),
+        &[LEAF.to_vec(), CA.to_vec()],
+        Config::builder().build(),
+    );
+    assert_eq!(check::exit_code(&coll), 0);
+    assert_that!(coll.to_string().starts_with("OCSP: good (this update: "));
+}
+
+#[test]
+fn test_revoked() {
+    let coll = ocsp::check(
+        Some(REVOKED),
+        &[REVOKED_LEAF.to_vec(), CA.to_vec()],
+        Config::builder().build(),
+    );
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_that!(coll.to_string().starts_with("OCSP: revoked ("));
+}
+
+#[test]
+fn test_forged() {
+    let coll = ocsp::check(
+        Some(FORGED),
+        &[LEAF.to_vec(), CA.to_vec()],
+        Config::builder().build(),
+    );
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_eq!(
+        coll.to_string(),
+        "OCSP: stapled response not signed by the issuer (!!)"
+    );
+}
+
+#[test]
+fn test_response_for_other_certificate() {
+    let coll = ocsp::check(
+        Some(GOOD),
+        &[REVOKED_LEAF.to_vec(), CA.to_vec()],
+        Config::builder().build(),
+    );
+    assert_eq!(check::exit_code(&coll), 1);
+    assert_eq!(
+        coll.to_string(),
+        "OCSP: response does not cover the certificate (!)"
+    );
+}
+
+#[test]
+fn test_issuer_missing() {
+    let coll = ocsp::check(Some(GOOD), &[LEAF.to_vec()], Config::builder().build());
+    assert_eq!(check::exit_code(&coll), 3);
+}
+
+#[test]
+fn test_missing_staple() {
+    let coll = ocsp::check(None, &[LEAF.to_vec()], Config::builder().build());
+    assert_eq!(check::exit_code(&coll), 0);
+    assert_eq!(coll.to_string(), "OK\nNo OCSP response stapled");
+
+    let coll = ocsp::check(
+        None,
+        &[LEAF.to_vec()],
+        Config::builder()
+            .missing_staple(MissingStaple::Crit)
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_eq!(coll.to_string(), "No OCSP response stapled (!!)");
+}
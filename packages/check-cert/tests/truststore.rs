@@ -69,3 +69,109 @@ fn test_load_cert_store() {
     assert_that!(store_from_file).has_length(3);
     assert_that!(store_from_dir).has_length(3);
 }
+
+#[test]
+fn test_load_certs() {
+    let leaf = include_bytes!("../assets/ocsp-leaf.der");
+    let ca = include_bytes!("../assets/ocsp-ca.der");
+
+    let dir = TempDir::new().unwrap();
+    let pem_path = dir.path().join("chain.pem");
+    std::fs::write(&pem_path, [to_pem(leaf), to_pem(ca)].concat()).unwrap();
+    let der_path = dir.path().join("leaf.der");
+    std::fs::write(&der_path, leaf).unwrap();
+
+    assert_eq!(
+        truststore::load_certs(&pem_path, None).unwrap(),
+        vec![leaf.to_vec(), ca.to_vec()]
+    );
+    assert_eq!(
+        truststore::load_certs(&der_path, None).unwrap(),
+        vec![leaf.to_vec()]
+    );
+}
+
+#[test]
+fn test_load_certs_without_certificate() {
+    let dir = TempDir::new().unwrap();
+    let file_path = dir.path().join("empty.pem");
+    std::fs::write(&file_path, b"").unwrap();
+
+    assert!(truststore::load_certs(&file_path, None).is_err());
+    assert!(truststore::load_certs(&dir.path().join("missing.pem"), None).is_err());
+}
+
+#[test]
+fn test_load_pkcs7_bundle() {
+    let leaf = include_bytes!("../assets/ocsp-leaf.der");
+    let ca = include_bytes!("../assets/ocsp-ca.der");
+    let bundle = include_bytes!("../assets/ocsp-chain.p7b");
+    let pem = openssl::pkcs7::Pkcs7::from_der(bundle)
+        .unwrap()
+        .to_pem()
+        .unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let der_path = dir.path().join("chain.p7b");
+    std::fs::write(&der_path, bundle).unwrap();
+    let pem_path = dir.path().join("chain.p7c");
+    std::fs::write(&pem_path, pem).unwrap();
+
+    assert_eq!(
+        truststore::sniff(bundle),
+        Some(truststore::CertFormat::Pkcs7)
+    );
+    for path in [&der_path, &pem_path] {
+        assert_eq!(
+            truststore::load_certs(path, None).unwrap(),
+            vec![leaf.to_vec(), ca.to_vec()]
+        );
+        assert_eq!(
+            truststore::load_certs(path, Some(truststore::CertFormat::Pkcs7)).unwrap(),
+            vec![leaf.to_vec(), ca.to_vec()]
+        );
+    }
+}
+
+#[test]
+fn test_load_certs_with_wrong_format() {
+    let leaf = include_bytes!("../assets/ocsp-leaf.der");
+
+    let dir = TempDir::new().unwrap();
+    let der_path = dir.path().join("leaf.der");
+    std::fs::write(&der_path, leaf).unwrap();
+    let garbage_path = dir.path().join("garbage.der");
+    std::fs::write(&garbage_path, &leaf[..100]).unwrap();
+
+    assert_eq!(truststore::sniff(leaf), Some(truststore::CertFormat::Der));
+    assert!(truststore::load_certs(&der_path, Some(truststore::CertFormat::Pem)).is_err());
+    assert!(truststore::load_certs(&der_path, Some(truststore::CertFormat::Pkcs7)).is_err());
+    assert!(truststore::load_certs(&garbage_path, None).is_err());
+}
+
+#[test]
+fn test_select_leaf() {
+    let leaf = include_bytes!("../assets/ocsp-leaf.der").to_vec();
+    let other = include_bytes!("../assets/crl-leaf.der").to_vec();
+    let ca = include_bytes!("../assets/ocsp-ca.der").to_vec();
+    let certs = [ca.clone(), leaf, other];
+
+    assert_eq!(truststore::select_leaf(&certs, None, None).unwrap(), 1);
+    assert_eq!(
+        truststore::select_leaf(&certs, Some("crl.example.com"), None).unwrap(),
+        2
+    );
+    assert_eq!(
+        truststore::select_leaf(&certs, Some("crl.example.com"), Some(0)).unwrap(),
+        0
+    );
+    assert!(truststore::select_leaf(&certs, Some("other.example.com"), None).is_err());
+    assert!(truststore::select_leaf(&certs, None, Some(3)).is_err());
+    assert!(truststore::select_leaf(&[ca.clone(), ca.clone()], None, None).is_err());
+
+    let ip_san = include_bytes!("../assets/ip-san.der").to_vec();
+    assert_eq!(
+        truststore::select_leaf(&[ca, ip_san], Some("192.0.2.1"), None).unwrap(),
+        1
+    );
+}
@@ -7,14 +7,73 @@ use check_cert::checker::verification::{self, Config};
 
 #[test]
 fn test_verification_with_canned_certs() {
-    // Taken from `rust-openssl`
-    let ca = include_bytes!("../assets/root-ca.der");
-    let cert = include_bytes!("../assets/cert.der");
+    let ca = include_bytes!("../assets/ocsp-ca.der");
+    let cert = include_bytes!("../assets/ocsp-leaf.der");
 
     let trust_store = vec![ca.to_vec()];
     let config = Config::builder().trust_store(&trust_store).build();
 
     let coll = verification::check(&[cert.to_vec()], config);
     assert_eq!(check::exit_code(&coll), 0);
-    assert_eq!(coll.to_string(), "Verification: OK\nVerification: OK");
+    assert_eq!(
+        coll.to_string(),
+        "Chain verified (2 certificate(s))\nChain verified (2 certificate(s))"
+    );
+}
+
+#[test]
+fn test_verification_with_sent_root() {
+    let ca = include_bytes!("../assets/ocsp-ca.der");
+    let cert = include_bytes!("../assets/ocsp-leaf.der");
+
+    let trust_store = vec![ca.to_vec()];
+    let config = Config::builder().trust_store(&trust_store).build();
+
+    // The root sent by the server counts once.
+    let coll = verification::check(&[cert.to_vec(), ca.to_vec()], config);
+    assert_eq!(check::exit_code(&coll), 0);
+    assert_eq!(
+        coll.to_string(),
+        "Chain verified (2 certificate(s))\nChain verified (2 certificate(s))"
+    );
+}
+
+#[test]
+fn test_verification_with_unknown_issuer() {
+    let cert = include_bytes!("../assets/ocsp-leaf.der");
+
+    let trust_store = vec![include_bytes!("../assets/root-ca.der").to_vec()];
+    let config = Config::builder().trust_store(&trust_store).build();
+
+    let coll = verification::check(&[cert.to_vec()], config);
+    assert_eq!(check::exit_code(&coll), 2);
+    assert_eq!(
+        coll.to_string(),
+        "Chain verification failed: unable to get local issuer certificate (!!)\n\
+        Chain verification failed: unable to get local issuer certificate"
+    );
+}
+
+#[test]
+fn test_verification_with_self_signed() {
+    let ca = include_bytes!("../assets/ocsp-ca.der");
+
+    let trust_store = vec![];
+    let coll = verification::check(
+        &[ca.to_vec()],
+        Config::builder().trust_store(&trust_store).build(),
+    );
+    assert_eq!(check::exit_code(&coll), 2);
+    assert!(coll
+        .to_string()
+        .starts_with("Chain verification failed: self-signed certificate (!!)"));
+
+    let coll = verification::check(
+        &[ca.to_vec()],
+        Config::builder()
+            .trust_store(&trust_store)
+            .allow_self_signed(true)
+            .build(),
+    );
+    assert_eq!(check::exit_code(&coll), 0);
 }